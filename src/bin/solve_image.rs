@@ -1,62 +1,492 @@
+use std::{
+    io::{self, Read},
+    process::ExitCode,
+};
+
 use linija::{
-    edgemap::{EdgeMap, PathType},
+    Connectivity, Error,
+    board::Board,
+    color_scheme,
+    detection::{Detection, Interpretation},
+    detector::{ColorTableDetector, ShapeDetector, TileDetector},
+    edgemap::EdgeMap,
     grid::Grid,
-    image::{Image, png},
-    tile::Tile,
+    image::{self, Image},
+    overlay::Overlay,
+    screen::Screen,
+    tile::DetectOptions,
 };
 
-fn main() {
-    let files = std::env::args().skip(1).collect::<Vec<String>>();
+#[derive(Clone, Default)]
+struct Settings {
+    options: DetectOptions,
+
+    // Whether to use the shape detector instead of the color table detector
+    shape_detector: bool,
+
+    // File to write a detection debug overlay to.  With several input files or
+    // frames, one file per input and frame is written, see overlay_file().
+    debug_overlay: Option<String>,
+
+    // Whether to print per-tile detection metrics and ignored segments
+    report: bool,
+}
+
+fn main() -> ExitCode {
+    let mut frames = false;
+    let mut settings = Settings::default();
+    let mut files = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => frames = true,
+            "--shape-detector" => settings.shape_detector = true,
+            "--report" => settings.report = true,
+            "--infer-color-scheme" => settings.options.infer_color_scheme = true,
+            "--color-tolerance" => {
+                let Some(tolerance) = args.next().and_then(|arg| arg.parse().ok()) else {
+                    return usage_error("--color-tolerance requires a numeric distance");
+                };
+
+                settings.options.color_tolerance = tolerance;
+            }
+            "--connectivity" => {
+                settings.options.connectivity = match args.next().as_deref() {
+                    Some("4") => Connectivity::Four,
+                    Some("8") => Connectivity::Eight,
+                    _ => return usage_error("--connectivity requires 4 or 8"),
+                };
+            }
+            "--debug-overlay" => {
+                let Some(file) = args.next() else {
+                    return usage_error("--debug-overlay requires a file name");
+                };
+
+                settings.debug_overlay = Some(file);
+            }
+            "--color-schemes" => {
+                let Some(file) = args.next() else {
+                    return usage_error("--color-schemes requires a file name");
+                };
+
+                if let Err(err) = color_scheme::load_config(&file) {
+                    eprintln!("{}: {}", file, err);
+
+                    return ExitCode::FAILURE;
+                }
+            }
+            _ => files.push(arg),
+        }
+    }
 
     if files.is_empty() {
-        panic!("No .png images specified on the command line");
+        return usage_error("No images specified on the command line");
     }
 
-    for file in files {
+    let mut failed = false;
+    let several_files = files.len() > 1;
+
+    for (index, file) in files.iter().enumerate() {
         println!("Solving {}", file);
         println!();
 
-        let image: Box<dyn Image> = png::open(&file).unwrap();
+        let mut settings = settings.clone();
+
+        if several_files {
+            settings.debug_overlay = settings
+                .debug_overlay
+                .map(|debug_overlay| overlay_file(&debug_overlay, &index.to_string()));
+        }
+
+        let result = if frames {
+            solve_frames(file, &settings)
+        } else {
+            solve(file, &settings)
+        };
+
+        if let Err(err) = result {
+            eprintln!("{}: {}", file, err);
+            println!();
+
+            failed = true;
+        }
+
+        println!("===");
+        println!();
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+// Reports a missing or invalid command line argument
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{}", message);
+
+    ExitCode::FAILURE
+}
+
+fn solve(file: &str, settings: &Settings) -> Result<(), Error> {
+    // "-" reads the image from stdin, so that screenshots can be piped in directly
+    let image: Box<dyn Image> = if file == "-" {
+        image::from_reader(io::stdin().lock())?
+    } else {
+        image::open(file)?
+    };
+
+    let dithered = image::is_dithered(&*image);
+
+    let (image, settings) = undither(image, dithered, settings);
+    let (settings, options) = (&settings, &settings.options);
+
+    if dithered {
+        println!(
+            "Dithered image, averaging out the dither with color tolerance {}",
+            options.color_tolerance
+        );
+        println!();
+    }
+
+    if settings.report {
+        println!("Screen: {}", Screen::classify(&*image, options));
+        println!();
+    }
+
+    let board = locate(&*image, settings)?;
+
+    if settings.shape_detector {
+        println!("Color scheme: unknown, classifying tiles by shape");
+        println!();
 
-        let tiles = Tile::detect_tiles(&*image).unwrap();
+        let detection = detect(&*image, &board, &ShapeDetector::default(), settings)?;
+
+        if settings.report {
+            print_report(&detection);
+        }
+
+        return print_solution(&detection, None);
+    }
 
-        let grid = Grid::from_tiles(&tiles);
+    if options.color_tolerance > 0 {
+        let p = image.pixel(board.x, board.y);
+
+        let border_match = color_scheme::classify_border_color(&[p.0, p.1, p.2]);
+
+        println!(
+            "Border color {:?} matched with distance {:.1} and confidence {:.2}",
+            [p.0, p.1, p.2],
+            f64::from(border_match.distance).sqrt(),
+            border_match.confidence
+        );
+        println!();
+    }
+
+    let color_scheme = board.detect_color_scheme(&*image, options)?;
+
+    println!("Color scheme: {}", color_scheme.name);
+    println!();
+
+    let detector = ColorTableDetector {
+        color_scheme: Some(&color_scheme),
+        options: *options,
+    };
+
+    let detection = detect(&*image, &board, &detector, settings)?;
+
+    if settings.report {
+        print_report(&detection);
+    }
+
+    print_solution(
+        &detection,
+        Some(&|grid| EdgeMap::detect_drawn(&*image, grid, &color_scheme, options)),
+    )
+}
+
+// Detects the tiles of a board, writing a debug overlay of the detection if one is
+// requested
+fn detect(
+    image: &dyn Image,
+    board: &Board,
+    detector: &dyn TileDetector,
+    settings: &Settings,
+) -> Result<Detection, Error> {
+    let Some(debug_overlay) = &settings.debug_overlay else {
+        return board.detect(image, detector, None);
+    };
+
+    let mut overlay = Overlay::new(image);
+
+    let detection = board.detect(image, detector, Some(&mut overlay));
+
+    if let Ok(detection) = &detection
+        && let Ok(grid) = Grid::from_tiles(&detection.tiles())
+    {
+        overlay.grid(&grid);
+    }
 
-        let (edgemap, trail_triangle, trail_diamond, trail_square) =
-            EdgeMap::find(&grid, |edgemap| {
-                let trail_triangle = edgemap.trail(&grid, PathType::Triangle);
-                let trail_diamond = edgemap.trail(&grid, PathType::Diamond);
-                let trail_square = edgemap.trail(&grid, PathType::Square);
+    // Write the overlay even if detection failed, as that's when it's most useful
+    image::png::save(&overlay, debug_overlay)?;
 
-                if let Some(trail_triangle) = trail_triangle
-                    && let Some(trail_diamond) = trail_diamond
-                    && let Some(trail_square) = trail_square
-                {
-                    Err((edgemap.clone(), trail_triangle, trail_diamond, trail_square))
-                } else {
-                    Ok(())
+    detection
+}
+
+// Derives the name of a debug overlay file from the given one by appending a
+// suffix to the file stem, e.g. "overlay.png" becomes "overlay-1.png"
+fn overlay_file(debug_overlay: &str, suffix: &str) -> String {
+    let stem_end = debug_overlay
+        .rfind('.')
+        .filter(|&dot| dot > debug_overlay.rfind('/').map_or(0, |slash| slash + 1))
+        .unwrap_or(debug_overlay.len());
+
+    format!(
+        "{}-{}{}",
+        &debug_overlay[..stem_end],
+        suffix,
+        &debug_overlay[stem_end..]
+    )
+}
+
+fn print_report(detection: &Detection) {
+    let grid = Grid::from_tiles(&detection.tiles()).ok();
+
+    for detected in &detection.tiles {
+        let bounds = &detected.bounds;
+
+        print!(
+            "{} at ({}, {}): {} pixels in ({}, {})-({}, {}), {} hole(s)",
+            detected.tile.tile_type.as_char(),
+            detected.tile.grid_x,
+            detected.tile.grid_y,
+            detected.area,
+            bounds.min_x,
+            bounds.min_y,
+            bounds.max_x,
+            bounds.max_y,
+            detected.num_holes
+        );
+
+        if detected.occluded {
+            print!(", occluded");
+        }
+
+        if !detected.alternatives.is_empty() {
+            print!(
+                ", uncertain (or {})",
+                detected
+                    .alternatives
+                    .iter()
+                    .map(|tile_type| tile_type.as_char().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        if let Some(grid) = &grid {
+            print!(", {:.1} px from grid", detected.grid_distance(grid));
+        }
+
+        println!();
+    }
+
+    for ignored in &detection.ignored {
+        let bounds = &ignored.bounds;
+
+        let class = match ignored.class {
+            Some(class) => format!("color {} ", class),
+            None => String::new(),
+        };
+
+        println!(
+            "Ignored {}segment: {} pixels in ({}, {})-({}, {}), {:?}",
+            class,
+            ignored.area,
+            bounds.min_x,
+            bounds.min_y,
+            bounds.max_x,
+            bounds.max_y,
+            ignored.reason
+        );
+    }
+
+    println!();
+}
+
+// Solves the first board in a recording that is detected identically in two
+// consecutive frames, so that frames captured mid-transition are skipped.
+fn solve_frames(file: &str, settings: &Settings) -> Result<(), Error> {
+    let frames = if file == "-" {
+        let mut bytes = Vec::new();
+
+        io::stdin().lock().read_to_end(&mut bytes)?;
+
+        image::frames_from_bytes(bytes)?
+    } else {
+        image::open_frames(file)?
+    };
+
+    let mut previous_tiles = None;
+
+    for (i, frame) in frames.enumerate() {
+        let frame = frame?;
+
+        let dithered = image::is_dithered(&*frame);
+
+        let (frame, mut settings) = undither(frame, dithered, settings);
+
+        settings.debug_overlay = settings
+            .debug_overlay
+            .map(|debug_overlay| overlay_file(&debug_overlay, &format!("frame{}", i)));
+
+        let settings = &settings;
+
+        let detector: &dyn TileDetector = if settings.shape_detector {
+            &ShapeDetector::default()
+        } else {
+            &ColorTableDetector {
+                color_scheme: None,
+                options: settings.options,
+            }
+        };
+
+        let detection =
+            locate(&*frame, settings).and_then(|board| detect(&*frame, &board, detector, settings));
+
+        match detection {
+            Ok(detection) if !detection.tiles.is_empty() => {
+                let tiles = detection.tiles();
+
+                if previous_tiles.as_ref() == Some(&tiles) {
+                    println!("Stable board found at frame {}", i);
+                    println!();
+
+                    return print_solution(&detection, None);
                 }
-            })
-            .unwrap_err();
-
-        println!("{}", edgemap.printable(&grid));
-        println!();
-
-        for trail in [trail_triangle, trail_diamond, trail_square] {
-            if !trail.is_empty() {
-                println!(
-                    "{}",
-                    trail
-                        .into_iter()
-                        .map(|point| format!("{:?}", point))
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                );
+
+                previous_tiles = Some(tiles);
             }
+            // Failing to write the debug overlay
+            Err(err @ Error::Io(_)) => return Err(err),
+            _ => previous_tiles = None,
+        }
+    }
+
+    Err(Error::MalformedTileLayout(
+        "No frame contains a stable board".to_string(),
+    ))
+}
+
+// GIF recordings are usually reduced to a palette by dithering, which leaves hardly
+// any pixels with the colors of the color scheme.  Averaging out the dither restores
+// the colors closely enough to detect the board with a higher color tolerance.
+fn undither(
+    image: Box<dyn Image>,
+    dithered: bool,
+    settings: &Settings,
+) -> (Box<dyn Image>, Settings) {
+    let mut settings = settings.clone();
+
+    if !dithered {
+        return (image, settings);
+    }
+
+    settings.options.color_tolerance = settings
+        .options
+        .color_tolerance
+        .max(image::UNDITHERED_COLOR_TOLERANCE);
+
+    (image::undither(&*image), settings)
+}
+
+// The shape detector doesn't need to know the color scheme, so if the board can't
+// be located by its border color, try the whole image instead
+fn locate(image: &dyn Image, settings: &Settings) -> Result<Board, Error> {
+    match Board::locate(image, &settings.options) {
+        Err(Error::BoardNotFound) if settings.shape_detector => Ok(Board {
+            x: 0,
+            y: 0,
+            width: image.width(),
+            height: image.height(),
+        }),
+        result => result,
+    }
+}
+
+// Prints the solution of a detected board.  If a way to detect the paths already
+// drawn on the board is given, prints those too, and the solution that continues
+// them if there is one.
+fn print_solution(
+    detection: &Detection,
+    detect_drawn: Option<&dyn Fn(&Grid) -> EdgeMap>,
+) -> Result<(), Error> {
+    let Interpretation {
+        tiles,
+        grid,
+        mut solution,
+    } = detection.solve()?;
+
+    println!("Level: {}", grid.level_key());
+    println!();
+
+    let mut reread = false;
+
+    for (detected, tile) in detection.tiles.iter().zip(&tiles) {
+        if detected.tile.tile_type != tile.tile_type {
+            println!(
+                "Read {} at ({}, {}) as {} to make the board solvable",
+                detected.tile.tile_type.as_char(),
+                tile.grid_x,
+                tile.grid_y,
+                tile.tile_type.as_char()
+            );
+
+            reread = true;
         }
+    }
+
+    if reread {
         println!();
+    }
 
-        println!("===");
+    if let Some(drawn) = detect_drawn.map(|detect_drawn| detect_drawn(&grid))
+        && !drawn.is_empty()
+    {
+        println!("Drawn paths:");
+        println!();
+        println!("{}", drawn.printable(&grid));
         println!();
+
+        match EdgeMap::solve_from(&grid, &drawn) {
+            Ok(continued) => solution = continued,
+            Err(Error::Unsolvable) => {
+                println!("The drawn paths aren't part of any solution");
+                println!();
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    println!("{}", solution.edgemap.printable(&grid));
+    println!();
+
+    for trail in solution.trails() {
+        if !trail.is_empty() {
+            println!(
+                "{}",
+                trail
+                    .iter()
+                    .map(|point| format!("{:?}", point))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
     }
+    println!();
+
+    Ok(())
 }
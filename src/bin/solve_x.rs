@@ -1,10 +1,12 @@
-use std::{thread::sleep, time::Duration};
+use std::{process, thread::sleep, time::Duration};
 
 use linija::{
-    edgemap::{EdgeMap, PathType},
-    grid::Grid,
+    Error, color_scheme,
+    detector::{ColorTableDetector, TileDetector},
+    edgemap::EdgeMap,
     image,
-    tile::Tile,
+    screen::Screen,
+    tile::{DetectOptions, Tile},
 };
 use x11rb::{
     connection::Connection,
@@ -15,38 +17,126 @@ use x11rb::{
     rust_connection::RustConnection,
 };
 
+// Maximum distance in pixels of a tile from its grid intersection
+const MAX_GRID_DISTANCE: f64 = 2.0;
+
+// How often and for how long to look for the solved board after drawing the solution
+const SOLVED_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+const SOLVED_CHECKS: usize = 30;
+
 fn main() {
-    let mut conn = Conn::new(None);
+    let mut save_screenshot = None;
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save-screenshot" => {
+                let Some(file) = args.next() else {
+                    fail(Error::Config(
+                        "--save-screenshot requires a file name".to_string(),
+                    ));
+                };
+
+                save_screenshot = Some(file);
+            }
+            "--color-schemes" => {
+                let Some(file) = args.next() else {
+                    fail(Error::Config(
+                        "--color-schemes requires a file name".to_string(),
+                    ));
+                };
+
+                color_scheme::load_config(&file).unwrap_or_else(|err| fail(err));
+            }
+            _ => fail(Error::Config(format!("Unknown argument '{}'", arg))),
+        }
+    }
+
+    let mut conn = Conn::new(None).unwrap_or_else(|err| fail(err));
 
     let window_name = "LYNE";
 
     let Some(window) = conn.find_window_by_name(window_name) else {
-        panic!("Can't find X window '{}'", window_name);
+        fail(Error::X11(format!("Can't find X window '{}'", window_name)));
     };
 
-    let image = image::x::from_window(&conn.conn, window).unwrap();
+    let image = image::x::from_window(&conn.conn, window).unwrap_or_else(|err| fail(err));
 
-    let tiles = Tile::detect_tiles(&*image).unwrap();
+    // Save the screenshot before attempting detection, so that detection failures
+    // can be reproduced offline with solve_image
+    if let Some(file) = save_screenshot {
+        image::png::save(&*image, &file).unwrap_or_else(|err| fail(err));
+    }
 
-    let grid = Grid::from_tiles(&tiles);
+    let options = DetectOptions::default();
 
-    let (edgemap, trail_triangle, trail_diamond, trail_square) = EdgeMap::find(&grid, |edgemap| {
-        let trail_triangle = edgemap.trail(&grid, PathType::Triangle);
-        let trail_diamond = edgemap.trail(&grid, PathType::Diamond);
-        let trail_square = edgemap.trail(&grid, PathType::Square);
+    let color_scheme = Tile::detect_color_scheme(&*image, &options).unwrap_or_else(|err| fail(err));
 
-        if let Some(trail_triangle) = trail_triangle
-            && let Some(trail_diamond) = trail_diamond
-            && let Some(trail_square) = trail_square
-        {
-            Err((edgemap.clone(), trail_triangle, trail_diamond, trail_square))
-        } else {
-            Ok(())
+    let detector = ColorTableDetector {
+        color_scheme: Some(&color_scheme),
+        options,
+    };
+
+    let detection = detector
+        .detect(&*image, None)
+        .unwrap_or_else(|err| fail(err));
+
+    let interpretation = detection.solve().unwrap_or_else(|err| fail(err));
+
+    let (grid, solution) = (&interpretation.grid, &interpretation.solution);
+
+    println!("Level: {}", grid.level_key());
+    println!();
+
+    // A tile that's off its grid intersection means detection misread something,
+    // and one that something hides may have been guessed, so don't click on a board
+    // we're unsure of
+    for detected in &detection.tiles {
+        if detected.occluded && !detected.alternatives.is_empty() {
+            fail(Error::MalformedTileLayout(format!(
+                "Tile {:?} is partly hidden",
+                detected.tile
+            )));
         }
-    })
-    .unwrap_err();
 
-    println!("{}", edgemap.printable(&grid));
+        let distance = detected.grid_distance(grid);
+
+        if distance > MAX_GRID_DISTANCE {
+            fail(Error::MalformedTileLayout(format!(
+                "Tile {:?} is {:.1} pixels off the grid",
+                detected.tile, distance
+            )));
+        }
+    }
+
+    // Drawing the trails from their terminals redraws any paths the player already
+    // started, which only works out if those are part of the solution.  Anything else
+    // has to be cleared first.
+    let drawn = EdgeMap::detect_drawn(&*image, grid, &color_scheme, &options);
+
+    let continued;
+
+    let solution = if drawn.is_empty() {
+        solution
+    } else {
+        println!("Drawn paths:");
+        println!();
+        println!("{}", drawn.printable(grid));
+        println!();
+
+        continued = EdgeMap::solve_from(grid, &drawn).unwrap_or_else(|err| match err {
+            Error::Unsolvable => {
+                eprintln!("The drawn paths aren't part of any solution, clear the board first");
+                process::exit(1);
+            }
+            err => fail(err),
+        });
+
+        &continued
+    };
+
+    println!("{}", solution.edgemap.printable(grid));
 
     let max_pixels_per_second = 650;
     let hz: u16 = 20;
@@ -54,21 +144,19 @@ fn main() {
     let max_pixels_per_time_unit: i32 = max_pixels_per_second / i32::from(hz);
     let time_unit: Duration = Duration::from_millis(1000 / u64::from(hz));
 
-    for trail in [trail_triangle, trail_diamond, trail_square] {
+    for trail in solution.trails() {
         if !trail.is_empty() {
             println!();
 
             for (i, point) in trail.iter().enumerate() {
-                println!(
-                    "{:?}    {:?}",
-                    point,
-                    (grid.column_x[point.1], grid.row_y[point.0])
-                );
+                let (x, y) = grid.center(point.0, point.1);
+
+                println!("{:?}    ({:.1}, {:.1})", point, x, y);
 
                 conn.move_to(
                     window,
-                    grid.column_x[point.1],
-                    grid.row_y[point.0],
+                    x.round() as u16,
+                    y.round() as u16,
                     max_pixels_per_time_unit,
                     time_unit,
                 );
@@ -83,6 +171,39 @@ fn main() {
             sleep(time_unit);
         }
     }
+
+    // Check that the input worked.  LYNE only shows the solved board until it moves
+    // on to the next level, with an animation in which the tiles change color, so
+    // keep looking for a little while, and take either of those as solved too.
+    let level_key = grid.level_key();
+    let mut screen = Screen::InProgress(level_key.clone());
+
+    for _ in 0..SOLVED_CHECKS {
+        sleep(SOLVED_CHECK_INTERVAL);
+
+        let image = image::x::from_window(&conn.conn, window).unwrap_or_else(|err| fail(err));
+
+        screen = Screen::classify(&*image, &options);
+
+        match &screen {
+            Screen::Solved | Screen::Transition => break,
+            Screen::InProgress(next_level_key) if *next_level_key != level_key => break,
+            _ => {}
+        }
+    }
+
+    println!();
+    println!("Screen: {}", screen);
+
+    if screen == Screen::InProgress(level_key) || screen == Screen::NotPuzzle {
+        eprintln!("The board doesn't look solved");
+        process::exit(1);
+    }
+}
+
+fn fail(err: Error) -> ! {
+    eprintln!("{}", err);
+    process::exit(1);
 }
 
 struct Conn {
@@ -92,14 +213,15 @@ struct Conn {
 }
 
 impl Conn {
-    fn new(dpy_name: Option<&str>) -> Self {
-        let (conn, screen_num) = x11rb::connect(dpy_name).unwrap();
+    fn new(dpy_name: Option<&str>) -> Result<Self, Error> {
+        let (conn, screen_num) =
+            x11rb::connect(dpy_name).map_err(|err| Error::X11(err.to_string()))?;
 
-        Conn {
+        Ok(Conn {
             conn,
             screen_num,
             position: None,
-        }
+        })
     }
 
     fn find_window_by_name(&self, name: &str) -> Option<u32> {
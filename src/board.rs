@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+
+use crate::{
+    Error,
+    color_scheme::{ColorScheme, border_colors, match_border_color_in},
+    detection::Detection,
+    detector::TileDetector,
+    image::{Image, View},
+    overlay::Overlay,
+    pixels::Pixels,
+    tile::{DetectOptions, Tile},
+};
+
+// Boards smaller than this are not considered, to avoid matching stray pixels that
+// happen to have a border color
+const MIN_BOARD_SIZE: u32 = 64;
+
+// How far the top left corner of a board may be inside the bounding box of its
+// border, such as when the border continues into a window frame with rounded corners
+const MAX_CORNER_INSET: u32 = 16;
+
+// How far from the inner edge of the border to sample it, past any antialiasing
+const CORNER_MARGIN: u32 = 3;
+
+// The rectangle within an image that contains the LYNE game area, including its
+// border
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Board {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Board {
+    // Finds the game area in an arbitrary screenshot, by looking for the largest
+    // connected region of one of the color scheme border colors.  If the image
+    // starts with a border color, the entire image is assumed to be the game area.
+    pub fn locate<T: Image + ?Sized>(image: &T, options: &DetectOptions) -> Result<Self, Error> {
+        let whole_image = Board {
+            x: 0,
+            y: 0,
+            width: image.width(),
+            height: image.height(),
+        };
+
+        if image.width() == 0 || image.height() == 0 {
+            return Err(Error::BoardNotFound);
+        }
+
+        // Snapshot the border colors, rather than looking them up for every pixel
+        let border_colors = border_colors();
+        let is_border_color = |x, y| {
+            let p = image.pixel(x, y);
+
+            match_border_color_in(&border_colors, &[p.0, p.1, p.2], options.color_tolerance)
+                .is_some()
+        };
+
+        if is_border_color(0, 0) {
+            return Ok(whole_image);
+        }
+
+        if image.width() > u32::from(u16::MAX) || image.height() > u32::from(u16::MAX) {
+            return Err(Error::InvalidRegion(format!(
+                "Image dimensions {}x{} too large",
+                image.width(),
+                image.height()
+            )));
+        }
+
+        let mut border_pixels = Pixels::with_size(image.width(), image.height());
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                if is_border_color(x, y) {
+                    border_pixels.insert(x, y);
+                }
+            }
+        }
+
+        let mut best: Option<Board> = None;
+
+        for segment in border_pixels.segment_all() {
+            let bounds = segment.bounds;
+
+            let mut board = Board {
+                x: u32::from(bounds.min_x),
+                y: u32::from(bounds.min_y),
+                width: u32::from(bounds.max_x - bounds.min_x) + 1,
+                height: u32::from(bounds.max_y - bounds.min_y) + 1,
+            };
+
+            if board.width < MIN_BOARD_SIZE || board.height < MIN_BOARD_SIZE {
+                continue;
+            }
+
+            // Color scheme detection samples the top left corner of the board.  In a
+            // window with rounded corners whose frame has the border color, that corner
+            // is outside the window, and the top of the frame may be a title bar in a
+            // similar color.  In that case, move the corner inward through the frame to
+            // the edge of the game area, which is surrounded by the border proper.
+            let has_border_color = |inset: &u32| is_border_color(board.x + inset, board.y + inset);
+
+            let inset = match (0..MAX_CORNER_INSET).find(has_border_color) {
+                Some(0) => 0,
+                Some(first) => (first..board.width.min(board.height))
+                    .take_while(has_border_color)
+                    .last()
+                    .unwrap()
+                    .saturating_sub(CORNER_MARGIN)
+                    .max(first),
+                None => continue,
+            };
+
+            board.x += inset;
+            board.y += inset;
+            board.width -= inset;
+            board.height -= inset;
+
+            if best.is_none_or(|best| board.area() > best.area()) {
+                best = Some(board);
+            }
+        }
+
+        match best {
+            Some(board) => Ok(board),
+            // The color scheme can only be inferred for the image as a whole
+            None if options.infer_color_scheme => Ok(whole_image),
+            None => Err(Error::BoardNotFound),
+        }
+    }
+
+    pub fn detect_color_scheme<T: Image + ?Sized>(
+        &self,
+        image: &T,
+        options: &DetectOptions,
+    ) -> Result<Cow<'static, ColorScheme>, Error> {
+        Tile::detect_color_scheme(&self.view(image)?, options)
+    }
+
+    // Detects the tiles of the board with the given detector.  The returned tile
+    // coordinates are relative to the full image, and if an overlay of the full image
+    // is given, detection is drawn onto it.
+    pub fn detect<T: Image + ?Sized>(
+        &self,
+        image: &T,
+        detector: &dyn TileDetector,
+        mut overlay: Option<&mut Overlay>,
+    ) -> Result<Detection, Error> {
+        let view = self.view(image)?;
+
+        if let Some(overlay) = overlay.as_deref_mut() {
+            overlay.set_origin(self.x, self.y);
+        }
+
+        let result = detector.detect(&view, overlay.as_deref_mut());
+
+        if let Some(overlay) = overlay {
+            overlay.set_origin(0, 0);
+        }
+
+        result?.to_parent(&view)
+    }
+
+    fn view<'a, T: Image + ?Sized>(&self, image: &'a T) -> Result<View<'a, T>, Error> {
+        View::new(image, self.x, self.y, self.width, self.height)
+    }
+
+    fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 160x120 image with a frame of the given color and 8 pixels thick around
+    // (20, 10, 120, 100), on an unrelated background
+    struct Framed([u8; 3]);
+
+    impl Image for Framed {
+        fn width(&self) -> u32 {
+            160
+        }
+
+        fn height(&self) -> u32 {
+            120
+        }
+
+        fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+            let inside = |inset: u32| {
+                (20 + inset..140 - inset).contains(&x) && (10 + inset..110 - inset).contains(&y)
+            };
+
+            if inside(0) && !inside(8) {
+                (self.0[0], self.0[1], self.0[2])
+            } else {
+                (40, 40, 40)
+            }
+        }
+    }
+
+    #[test]
+    fn locate_tolerant() {
+        let options = |color_tolerance| DetectOptions {
+            color_tolerance,
+            ..DetectOptions::default()
+        };
+        let board = Board {
+            x: 20,
+            y: 10,
+            width: 120,
+            height: 100,
+        };
+
+        // The Original border color, and slightly off
+        let exact = Framed([233, 241, 223]);
+        let off = Framed([236, 244, 226]);
+
+        assert_eq!(Board::locate(&exact, &options(0)).unwrap(), board);
+        assert!(matches!(
+            Board::locate(&off, &options(0)),
+            Err(Error::BoardNotFound)
+        ));
+        assert!(matches!(
+            Board::locate(&off, &options(5)),
+            Err(Error::BoardNotFound)
+        ));
+        assert_eq!(Board::locate(&off, &options(6)).unwrap(), board);
+    }
+}
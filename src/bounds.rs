@@ -40,4 +40,13 @@ impl Bounds {
             && self.min_y > other.min_y
             && self.max_y < other.max_y
     }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Bounds {
+            min_x: self.min_x.min(other.min_x),
+            max_x: self.max_x.max(other.max_x),
+            min_y: self.min_y.min(other.min_y),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
 }
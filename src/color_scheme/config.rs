@@ -0,0 +1,282 @@
+use std::{fs, sync::RwLock};
+
+use serde_json::Value;
+
+use crate::{Error, color_scheme::ColorScheme};
+
+// Color schemes registered at runtime.  These are leaked so that they can be handed
+// out as &'static references just like the built-in ones, which is fine as they are
+// only registered once at startup.
+static USER_COLOR_SCHEMES: RwLock<Vec<&'static ColorScheme>> = RwLock::new(Vec::new());
+
+// Registers an additional color scheme.  It takes precedence over any previously
+// registered or built-in scheme with the same border color.
+pub fn register(color_scheme: ColorScheme) {
+    let color_scheme: &'static ColorScheme = Box::leak(Box::new(color_scheme));
+
+    let mut user_color_schemes = USER_COLOR_SCHEMES.write().unwrap();
+
+    user_color_schemes.retain(|existing| existing.border_color != color_scheme.border_color);
+    user_color_schemes.insert(0, color_scheme);
+}
+
+pub(super) fn user_color_schemes<R>(f: impl FnOnce(&[&'static ColorScheme]) -> R) -> R {
+    f(&USER_COLOR_SCHEMES.read().unwrap())
+}
+
+// Loads color schemes from a JSON file of the form:
+//
+// {
+//     "color_schemes": [
+//         {
+//             "name": "Streaming",
+//             "border_color": [233, 241, 223],
+//             "background_color": [121, 189, 154],
+//             "empty_cell_color": "#89c4a4",
+//             "triangle_outer_color": "#cef0b7",
+//             "triangle_color": [168, 219, 168],
+//             ...
+//         }
+//     ]
+// }
+//
+// Colors can be given as [r, g, b] arrays or as "#rrggbb" strings.  Returns the
+// number of color schemes registered.
+pub fn load_config(file: &str) -> Result<usize, Error> {
+    parse_config(&fs::read_to_string(file)?)
+}
+
+pub fn parse_config(json: &str) -> Result<usize, Error> {
+    let color_schemes = parse_color_schemes(json)?;
+    let num_color_schemes = color_schemes.len();
+
+    for color_scheme in color_schemes {
+        register(color_scheme);
+    }
+
+    Ok(num_color_schemes)
+}
+
+// Parses all color schemes of a configuration without registering any, so that an
+// invalid entry leaves the registered color schemes untouched
+fn parse_color_schemes(json: &str) -> Result<Vec<ColorScheme>, Error> {
+    let config: Value = serde_json::from_str(json).map_err(|err| Error::Config(err.to_string()))?;
+
+    let Some(entries) = config.get("color_schemes").and_then(Value::as_array) else {
+        return Err(Error::Config(
+            "Expected a \"color_schemes\" array".to_string(),
+        ));
+    };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let name = entry
+                .get("name")
+                .and_then(Value::as_str)
+                .map_or_else(|| format!("#{}", i), |name| name.to_string());
+
+            let color = |key: &str| {
+                entry
+                    .get(key)
+                    .ok_or_else(|| format!("missing \"{}\"", key))
+                    .and_then(parse_color)
+                    .map_err(|err| {
+                        Error::Config(format!("Color scheme {}: {}: {}", name, key, err))
+                    })
+            };
+
+            Ok(ColorScheme {
+                border_color: color("border_color")?,
+                background_color: color("background_color")?,
+                empty_cell_color: color("empty_cell_color")?,
+                triangle_outer_color: color("triangle_outer_color")?,
+                triangle_color: color("triangle_color")?,
+                diamond_outer_color: color("diamond_outer_color")?,
+                diamond_color: color("diamond_color")?,
+                square_outer_color: color("square_outer_color")?,
+                square_color: color("square_color")?,
+                connector_color: color("connector_color")?,
+                name: name.leak(),
+            })
+        })
+        .collect()
+}
+
+fn parse_color(value: &Value) -> Result<[u8; 3], String> {
+    match value {
+        Value::Array(components) if components.len() == 3 => {
+            let mut color = [0; 3];
+
+            for (component, value) in color.iter_mut().zip(components) {
+                *component = value
+                    .as_u64()
+                    .and_then(|value| u8::try_from(value).ok())
+                    .ok_or_else(|| format!("invalid color component {}", value))?;
+            }
+
+            Ok(color)
+        }
+        Value::String(hex) if hex.len() == 7 && hex.starts_with('#') && hex.is_ascii() => {
+            let mut color = [0; 3];
+
+            for (i, component) in color.iter_mut().enumerate() {
+                *component = u8::from_str_radix(&hex[1 + 2 * i..3 + 2 * i], 16)
+                    .map_err(|_| format!("invalid color \"{}\"", hex))?;
+            }
+
+            Ok(color)
+        }
+        _ => Err(format!(
+            "expected [r, g, b] or \"#rrggbb\", found {}",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::color_scheme::{COLOR_SCHEMES, color_schemes};
+
+    // The Original color scheme, with colors given in both supported notations
+    fn original() -> Value {
+        json!({
+            "name": "Streaming",
+            "border_color": [233, 241, 223],
+            "background_color": "#79bd9a",
+            "empty_cell_color": "#89C4A4",
+            "triangle_outer_color": [206, 240, 183],
+            "triangle_color": "#a8dba8",
+            "diamond_outer_color": [11, 72, 107],
+            "diamond_color": "#3b8686",
+            "square_outer_color": [190, 79, 35],
+            "square_color": "#c2785c",
+            "connector_color": [167, 219, 216],
+        })
+    }
+
+    fn parse(color_schemes: &[Value]) -> Result<Vec<ColorScheme>, Error> {
+        parse_color_schemes(&json!({ "color_schemes": color_schemes }).to_string())
+    }
+
+    #[test]
+    fn parse_colors() {
+        let color_schemes = parse(&[original()]).unwrap();
+        let builtin = COLOR_SCHEMES[0];
+
+        assert_eq!(color_schemes.len(), 1);
+        assert_eq!(color_schemes[0].name, "Streaming");
+        assert_eq!(color_schemes[0].border_color, builtin.border_color);
+        assert_eq!(color_schemes[0].background_color, builtin.background_color);
+        assert_eq!(color_schemes[0].empty_cell_color, builtin.empty_cell_color);
+        assert_eq!(color_schemes[0].shape_colors(), builtin.shape_colors());
+    }
+
+    #[test]
+    fn unnamed() {
+        let mut color_scheme = original();
+
+        color_scheme.as_object_mut().unwrap().remove("name");
+
+        let color_schemes = parse(&[original(), color_scheme]).unwrap();
+
+        assert_eq!(color_schemes[1].name, "#1");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(parse_color_schemes("{"), Err(Error::Config(_))));
+        assert!(matches!(parse_color_schemes("{}"), Err(Error::Config(_))));
+        assert!(matches!(
+            parse_color_schemes(r#"{"color_schemes": {}}"#),
+            Err(Error::Config(_))
+        ));
+        assert!(parse(&[]).unwrap().is_empty());
+
+        let mut missing = original();
+
+        missing.as_object_mut().unwrap().remove("square_color");
+
+        let Err(Error::Config(message)) = parse(&[missing]) else {
+            panic!("missing color accepted");
+        };
+
+        assert!(message.contains("Streaming"));
+        assert!(message.contains("square_color"));
+
+        for bad_color in [
+            json!([1, 2]),
+            json!([1, 2, 3, 4]),
+            json!([1, 2, 256]),
+            json!([1, -2, 3]),
+            json!([1.5, 2, 3]),
+            json!("#12345"),
+            json!("#1234567"),
+            json!("#12345g"),
+            json!("123456"),
+            json!("#ééé"),
+            json!(7),
+            json!(null),
+        ] {
+            let mut color_scheme = original();
+
+            color_scheme["triangle_color"] = bad_color.clone();
+
+            assert!(
+                matches!(parse(&[color_scheme]), Err(Error::Config(_))),
+                "{} accepted",
+                bad_color
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_entry_registers_nothing() {
+        let mut color_scheme = original();
+
+        color_scheme["name"] = json!("Never registered");
+        color_scheme["border_color"] = json!([1, 2, 3]);
+
+        let mut invalid = original();
+
+        invalid["connector_color"] = json!("blue");
+
+        let config = json!({ "color_schemes": [color_scheme, invalid] }).to_string();
+
+        assert!(parse_config(&config).is_err());
+        assert!(ColorScheme::by_name("Never registered").is_none());
+    }
+
+    #[test]
+    fn load() {
+        assert!(matches!(
+            load_config(concat!(env!("CARGO_MANIFEST_DIR"), "/missing.json")),
+            Err(Error::Io(_))
+        ));
+        assert!(matches!(
+            load_config(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")),
+            Err(Error::Config(_))
+        ));
+    }
+
+    // Registering would affect concurrently running tests, so overriding is checked
+    // on the list of known color schemes directly
+    #[test]
+    fn override_builtin() {
+        let color_scheme: &'static ColorScheme =
+            Box::leak(Box::new(parse(&[original()]).unwrap().remove(0)));
+
+        let all = color_schemes(&[color_scheme]).collect::<Vec<_>>();
+
+        assert_eq!(all.len(), COLOR_SCHEMES.len());
+        assert_eq!(all[0].name, "Streaming");
+        assert!(
+            all.iter()
+                .all(|color_scheme| color_scheme.name != "Original")
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use crate::{
+    Connectivity, Error,
+    color_scheme::ColorScheme,
+    detection::is_fringe,
+    image::Image,
+    pixels::Pixels,
+    shape::{Shape, classify_shape},
+};
+
+// Only this many of the most common colors are considered as shape colors
+const MAX_CANDIDATE_COLORS: usize = 16;
+
+// Colors covering less than 1/MIN_COLOR_FRACTION of the image are ignored
+const MIN_COLOR_FRACTION: usize = 2000;
+
+// Segments smaller than this are considered noise
+const MIN_SEGMENT_PIXELS: usize = 16;
+
+impl ColorScheme {
+    // Builds a color scheme for a screenshot with an unknown theme, using the
+    // topology of the segments of each of the image's dominant colors:
+    //
+    // * The connector color is the color most of whose segments have connector-like
+    //   hole counts.
+    // * An outer shape color is a color with exactly two segments (the terminals)
+    //   that enclose a segment of another color, which is then the corresponding
+    //   inner shape color.  Other segments of the outer color may be drawn paths.
+    // * The shape of the inner color's segments tells triangles, diamonds and
+    //   squares apart.
+    //
+    // Colors for shapes that don't occur in the image are set to a color that
+    // doesn't occur in the image either.
+    pub fn infer<T: Image + ?Sized>(image: &T) -> Result<Self, Error> {
+        if image.width() > u32::from(u16::MAX) || image.height() > u32::from(u16::MAX) {
+            return Err(Error::InvalidRegion(format!(
+                "Image dimensions {}x{} too large",
+                image.width(),
+                image.height()
+            )));
+        }
+
+        let border_color = {
+            let p = image.pixel(0, 0);
+
+            [p.0, p.1, p.2]
+        };
+
+        let mut histogram: BTreeMap<[u8; 3], usize> = BTreeMap::new();
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+
+                *histogram.entry([p.0, p.1, p.2]).or_default() += 1;
+            }
+        }
+
+        let mut colors = histogram
+            .iter()
+            .filter(|(color, _)| **color != border_color)
+            .map(|(color, count)| (*color, *count))
+            .collect::<Vec<_>>();
+
+        colors.sort_by_key(|(_, count)| Reverse(*count));
+
+        let min_pixels = (usize::try_from(image.width()).unwrap()
+            * usize::try_from(image.height()).unwrap()
+            / MIN_COLOR_FRACTION)
+            .max(MIN_SEGMENT_PIXELS);
+
+        // The most common color other than the border color is the background
+        let background_color = colors.first().map_or(border_color, |(color, _)| *color);
+
+        let mut candidates = colors
+            .iter()
+            .skip(1)
+            .filter(|(_, count)| *count >= min_pixels)
+            .take(MAX_CANDIDATE_COLORS)
+            .map(|(color, _)| (*color, Pixels::with_size(image.width(), image.height())))
+            .collect::<BTreeMap<_, _>>();
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+
+                if let Some(pixels) = candidates.get_mut(&[p.0, p.1, p.2]) {
+                    pixels.insert(x, y);
+                }
+            }
+        }
+
+        // Segments spanning a large part of the image are lines or backgrounds rather
+        // than tiles, such as blends along the edges of the game area
+        let segments = candidates
+            .into_iter()
+            .map(|(color, mut pixels)| {
+                let segments = pixels
+                    .segment_all()
+                    .into_iter()
+                    .filter(|segment| {
+                        segment.len() >= MIN_SEGMENT_PIXELS
+                            && 2 * u32::from(segment.bounds.max_x - segment.bounds.min_x)
+                                < image.width()
+                            && 2 * u32::from(segment.bounds.max_y - segment.bounds.min_y)
+                                < image.height()
+                    })
+                    .collect::<Vec<_>>();
+
+                (color, segments)
+            })
+            .collect::<Vec<_>>();
+
+        // Much smaller or thin segments are blends along the edges of tiles, which
+        // may have any of the colors
+        let reference_area = segments
+            .iter()
+            .flat_map(|(_, segments)| segments)
+            .map(Pixels::len)
+            .max()
+            .unwrap_or(0);
+
+        let segments = segments
+            .into_iter()
+            .map(|(color, mut segments)| {
+                segments.retain(|segment| !is_fringe(segment, reference_area));
+
+                (color, segments)
+            })
+            .filter(|(_, segments)| !segments.is_empty())
+            .collect::<Vec<_>>();
+
+        // Connectors have 2 to 4 holes, but the connector color may also occur
+        // elsewhere, so it only has to account for most of its segments
+        let connector_color = segments
+            .iter()
+            .map(|(color, segments)| {
+                let connectors = segments
+                    .iter()
+                    .filter(|segment| (2..=4).contains(&segment.num_holes(Connectivity::Four)))
+                    .count();
+
+                (color, connectors, segments.len())
+            })
+            .filter(|(_, connectors, count)| 2 * connectors > *count)
+            .max_by_key(|(_, connectors, _)| *connectors)
+            .map(|(color, _, _)| *color);
+
+        let mut triangle: Option<([u8; 3], [u8; 3])> = None;
+        let mut diamond: Option<([u8; 3], [u8; 3])> = None;
+        let mut square: Option<([u8; 3], [u8; 3])> = None;
+
+        for (outer_color, outer_segments) in &segments {
+            if Some(*outer_color) == connector_color {
+                continue;
+            }
+
+            // Exactly two segments of the outer color enclose segments of the inner
+            // color, and any others are paths or blends along tiles of other colors
+            let Some((inner_color, inner_segments)) =
+                segments.iter().find(|(inner_color, inner_segments)| {
+                    inner_color != outer_color
+                        && Some(*inner_color) != connector_color
+                        && outer_segments
+                            .iter()
+                            .filter(|outer| {
+                                inner_segments.iter().any(|inner| encloses(outer, inner))
+                            })
+                            .count()
+                            == 2
+                })
+            else {
+                continue;
+            };
+
+            let shape = classify_shape(inner_segments);
+
+            let slot = match shape {
+                Shape::Triangle => &mut triangle,
+                Shape::Diamond => &mut diamond,
+                Shape::Square => &mut square,
+            };
+
+            if let Some((_, existing_color)) = slot {
+                return Err(Error::MalformedTileLayout(format!(
+                    "Can't infer color scheme: colors {:?} and {:?} both look like {:?} tiles",
+                    existing_color, inner_color, shape
+                )));
+            }
+
+            *slot = Some((*outer_color, *inner_color));
+        }
+
+        if triangle.is_none() && diamond.is_none() && square.is_none() {
+            return Err(Error::UnknownColorScheme(border_color));
+        }
+
+        let unused_color = (0u32..)
+            .map(|i| {
+                let [_, r, g, b] = i.to_be_bytes();
+
+                [r, g, b]
+            })
+            .find(|color| !histogram.contains_key(color))
+            .unwrap();
+
+        let (triangle_outer_color, triangle_color) =
+            triangle.unwrap_or((unused_color, unused_color));
+        let (diamond_outer_color, diamond_color) = diamond.unwrap_or((unused_color, unused_color));
+        let (square_outer_color, square_color) = square.unwrap_or((unused_color, unused_color));
+
+        // Empty cells can't be told apart from other leftover colors (or may not
+        // occur at all), so they are assumed to blend in with the background
+        Ok(ColorScheme {
+            name: "Inferred",
+            border_color,
+            background_color,
+            empty_cell_color: background_color,
+            triangle_outer_color,
+            triangle_color,
+            diamond_outer_color,
+            diamond_color,
+            square_outer_color,
+            square_color,
+            connector_color: connector_color.unwrap_or(unused_color),
+        })
+    }
+}
+
+// Whether a segment lies within a hole of another one
+fn encloses(outer: &Pixels, inner: &Pixels) -> bool {
+    let Some((x, y)) = inner.iter().next() else {
+        return false;
+    };
+
+    inner.is_within(outer)
+        && outer
+            .holes(Connectivity::Four)
+            .iter()
+            .any(|hole| hole.contains(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::Board,
+        color_scheme::color_distance,
+        detector::{ColorTableDetector, TileDetector},
+        image::{self, UNDITHERED_COLOR_TOLERANCE, View},
+        tile::DetectOptions,
+    };
+
+    // A frame of a dithered GIF recording of a level, with its dither averaged out
+    // and each pixel set to the nearest color of its color scheme, as in a
+    // screenshot, and then given a theme of its own by rotating the color channels
+    struct Recolored {
+        image: Box<dyn Image>,
+        palette: Vec<[u8; 3]>,
+    }
+
+    impl Recolored {
+        fn new(index: usize) -> Self {
+            let frame = image::open_frames(concat!(env!("CARGO_MANIFEST_DIR"), "/z23.gif"))
+                .unwrap()
+                .nth(index)
+                .unwrap()
+                .unwrap();
+
+            let color_scheme = ColorScheme::by_name("Original").unwrap();
+
+            let mut palette = vec![
+                color_scheme.border_color,
+                color_scheme.background_color,
+                color_scheme.empty_cell_color,
+            ];
+
+            palette.extend(color_scheme.shape_colors());
+
+            Recolored {
+                image: image::undither(&*frame),
+                palette,
+            }
+        }
+
+        // The game area, which starts with the border
+        fn board(&self) -> View<'_, Self> {
+            let options = DetectOptions {
+                color_tolerance: UNDITHERED_COLOR_TOLERANCE,
+                ..Default::default()
+            };
+
+            let board = Board::locate(&*self.image, &options).unwrap();
+
+            View::new(self, board.x, board.y, board.width, board.height).unwrap()
+        }
+    }
+
+    impl Image for Recolored {
+        fn width(&self) -> u32 {
+            self.image.width()
+        }
+
+        fn height(&self) -> u32 {
+            self.image.height()
+        }
+
+        fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+            let p = self.image.pixel(x, y);
+
+            let [r, g, b] = *self
+                .palette
+                .iter()
+                .min_by_key(|color| color_distance(color, &[p.0, p.1, p.2]))
+                .unwrap();
+
+            (b, r, g)
+        }
+    }
+
+    fn recolor([r, g, b]: [u8; 3]) -> [u8; 3] {
+        [b, r, g]
+    }
+
+    #[test]
+    fn infer_recolored() {
+        let original = ColorScheme::by_name("Original").unwrap();
+
+        // The first frames of this level and of the next one
+        for index in [0, 275] {
+            let image = Recolored::new(index);
+            let board = image.board();
+            let color_scheme = ColorScheme::infer(&board).unwrap();
+
+            assert_eq!(color_scheme.border_color, recolor(original.border_color));
+            assert_eq!(
+                color_scheme.background_color,
+                recolor(original.background_color)
+            );
+            assert_eq!(
+                color_scheme.shape_colors(),
+                original.shape_colors().map(recolor)
+            );
+
+            if index == 0 {
+                let detector = ColorTableDetector {
+                    color_scheme: Some(&color_scheme),
+                    options: DetectOptions::default(),
+                };
+
+                assert_eq!(
+                    detector
+                        .detect(&board, None)
+                        .unwrap()
+                        .solve()
+                        .unwrap()
+                        .grid
+                        .level_key(),
+                    "t2tdds2S-223tdd2S-T3D4D3ss-ttdTdsss"
+                );
+            }
+        }
+    }
+}
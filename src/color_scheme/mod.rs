@@ -0,0 +1,441 @@
+mod config;
+mod infer;
+
+pub use config::{load_config, parse_config, register};
+
+#[derive(Clone, Debug)]
+pub struct ColorScheme {
+    pub name: &'static str,
+
+    // Color of the image border and of the cores of start pieces
+    pub border_color: [u8; 3],
+
+    pub background_color: [u8; 3],
+
+    // Color of empty cells and the connecting lines between cells
+    pub empty_cell_color: [u8; 3],
+
+    pub triangle_outer_color: [u8; 3],
+
+    // Color of connecting pieces and the inner regions of start pieces
+    pub triangle_color: [u8; 3],
+
+    pub diamond_outer_color: [u8; 3],
+
+    // Color of connecting pieces and the inner regions of start pieces
+    pub diamond_color: [u8; 3],
+
+    pub square_outer_color: [u8; 3],
+
+    // Color of connecting pieces and the inner regions of start pieces
+    pub square_color: [u8; 3],
+
+    pub connector_color: [u8; 3],
+}
+
+// The color schemes built into the game, in the order of the game's theme menu
+pub const COLOR_SCHEMES: [&ColorScheme; 12] = [
+    &ORIGINAL,
+    &DEEP_SPACE,
+    &MOONBASE,
+    &STONE_IN_FOCUS,
+    &VELVET_ICE,
+    &ELECTRO,
+    &TULIP,
+    &TANGERINE,
+    &KIND_OF_BLUE,
+    &SMOKING_ROOM,
+    &DESERT,
+    &PADDLEPOP,
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct BorderMatch {
+    pub border_color: [u8; 3],
+    pub color_scheme: &'static ColorScheme,
+
+    // Squared RGB distance between the sampled color and the scheme's border color
+    pub distance: u32,
+
+    // 1.0 for an unambiguous match, approaching 0.0 as the sampled color gets
+    // equally close to the border colors of two different schemes
+    pub confidence: f64,
+}
+
+// Returns all known color schemes, i.e. the given user-registered ones followed
+// by the built-in ones that they don't override
+fn color_schemes(
+    user_color_schemes: &[&'static ColorScheme],
+) -> impl Iterator<Item = &'static ColorScheme> {
+    user_color_schemes
+        .iter()
+        .copied()
+        .chain(COLOR_SCHEMES.into_iter().filter(|color_scheme| {
+            !user_color_schemes.iter().any(|user_color_scheme| {
+                user_color_scheme.border_color == color_scheme.border_color
+            })
+        }))
+}
+
+pub fn determine_color_scheme(border_color: &[u8]) -> Option<&'static ColorScheme> {
+    config::user_color_schemes(|user_color_schemes| {
+        color_schemes(user_color_schemes)
+            .find(|color_scheme| color_scheme.border_color == border_color)
+    })
+}
+
+// Finds the color scheme whose border color is nearest to the given color
+pub fn classify_border_color(border_color: &[u8; 3]) -> BorderMatch {
+    let mut best = (u32::MAX, COLOR_SCHEMES[0]);
+    let mut second_distance = u32::MAX;
+
+    config::user_color_schemes(|user_color_schemes| {
+        for color_scheme in color_schemes(user_color_schemes) {
+            let distance = color_distance(&color_scheme.border_color, border_color);
+
+            if distance < best.0 {
+                second_distance = best.0;
+                best = (distance, color_scheme);
+            } else if distance < second_distance {
+                second_distance = distance;
+            }
+        }
+    });
+
+    let (distance, color_scheme) = best;
+
+    let confidence = if second_distance == 0 {
+        0.0
+    } else {
+        1.0 - f64::from(distance).sqrt() / f64::from(second_distance).sqrt()
+    };
+
+    BorderMatch {
+        border_color: color_scheme.border_color,
+        color_scheme,
+        distance,
+        confidence,
+    }
+}
+
+// Returns the known border color that the given color corresponds to.  Exact
+// matching is tried first, and if that fails and a nonzero tolerance is given, the
+// nearest border color within that (non-squared) distance is accepted.
+pub fn match_border_color(color: &[u8; 3], tolerance: u32) -> Option<[u8; 3]> {
+    match_border_color_in(&border_colors(), color, tolerance)
+}
+
+// Returns the border colors of all known color schemes, so that many pixels can be
+// matched against them without looking up the color schemes for each
+pub fn border_colors() -> Vec<[u8; 3]> {
+    config::user_color_schemes(|user_color_schemes| {
+        color_schemes(user_color_schemes)
+            .map(|color_scheme| color_scheme.border_color)
+            .collect()
+    })
+}
+
+// Like match_border_color, but against the given border colors
+pub fn match_border_color_in(
+    border_colors: &[[u8; 3]],
+    color: &[u8; 3],
+    tolerance: u32,
+) -> Option<[u8; 3]> {
+    if border_colors.contains(color) {
+        return Some(*color);
+    }
+
+    if tolerance == 0 {
+        return None;
+    }
+
+    let mut best: Option<(u32, [u8; 3])> = None;
+
+    for border_color in border_colors {
+        let distance = color_distance(border_color, color);
+
+        if best.is_none_or(|(best_distance, _)| distance < best_distance) {
+            best = Some((distance, *border_color));
+        }
+    }
+
+    best.filter(|(distance, _)| *distance <= tolerance.saturating_pow(2))
+        .map(|(_, border_color)| border_color)
+}
+
+// Returns all color schemes whose border color is within the given (non-squared)
+// distance of the given color, nearest first.  Several schemes have border colors
+// that are only a few units apart, so with lossy images there can be more than one.
+pub fn border_color_candidates(color: &[u8; 3], tolerance: u32) -> Vec<&'static ColorScheme> {
+    let mut candidates = config::user_color_schemes(|user_color_schemes| {
+        color_schemes(user_color_schemes)
+            .map(|color_scheme| {
+                (
+                    color_distance(&color_scheme.border_color, color),
+                    color_scheme,
+                )
+            })
+            .filter(|(distance, _)| *distance <= tolerance.saturating_pow(2))
+            .collect::<Vec<_>>()
+    });
+
+    candidates.sort_by_key(|(distance, _)| *distance);
+
+    candidates
+        .into_iter()
+        .map(|(_, color_scheme)| color_scheme)
+        .collect()
+}
+
+// Squared Euclidean distance in RGB space
+pub fn color_distance(a: &[u8; 3], b: &[u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| u32::from(a.abs_diff(*b)).pow(2))
+        .sum()
+}
+
+impl ColorScheme {
+    // Returns all known color schemes, user-registered ones first
+    pub fn all() -> Vec<&'static ColorScheme> {
+        config::user_color_schemes(|user_color_schemes| color_schemes(user_color_schemes).collect())
+    }
+
+    // Looks up a color scheme by name, ignoring case
+    pub fn by_name(name: &str) -> Option<&'static ColorScheme> {
+        config::user_color_schemes(|user_color_schemes| {
+            color_schemes(user_color_schemes)
+                .find(|color_scheme| color_scheme.name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    // Returns the index into shape_colors() of the color nearest to the given pixel,
+    // if it is no further away than the given (non-squared) distance
+    pub fn nearest_shape_color(&self, pixel: &[u8; 3], tolerance: u32) -> Option<usize> {
+        self.shape_colors()
+            .iter()
+            .enumerate()
+            .map(|(i, color)| (color_distance(color, pixel), i))
+            .min()
+            .filter(|(distance, _)| *distance <= tolerance.saturating_pow(2))
+            .map(|(_, i)| i)
+    }
+
+    pub fn shape_colors(&self) -> [[u8; 3]; 7] {
+        [
+            self.triangle_outer_color,
+            self.triangle_color,
+            self.diamond_outer_color,
+            self.diamond_color,
+            self.square_outer_color,
+            self.square_color,
+            self.connector_color,
+        ]
+    }
+}
+
+const ORIGINAL: ColorScheme = ColorScheme {
+    name: "Original",
+    border_color: [233, 241, 223],
+    background_color: [121, 189, 154],
+    empty_cell_color: [137, 196, 164],
+    triangle_outer_color: [206, 240, 183],
+    triangle_color: [168, 219, 168],
+    diamond_outer_color: [11, 72, 107],
+    diamond_color: [59, 134, 134],
+    square_outer_color: [190, 79, 35],
+    square_color: [194, 120, 92],
+    connector_color: [167, 219, 216],
+};
+
+const DEEP_SPACE: ColorScheme = ColorScheme {
+    name: "Deep Space",
+    border_color: [239, 235, 223],
+    background_color: [30, 30, 30],
+    empty_cell_color: [59, 59, 57],
+    triangle_outer_color: [255, 165, 21],
+    triangle_color: [206, 137, 48],
+    diamond_outer_color: [0, 134, 255],
+    diamond_color: [74, 178, 255],
+    square_outer_color: [252, 31, 32],
+    square_color: [198, 0, 0],
+    connector_color: [209, 207, 184],
+};
+
+const MOONBASE: ColorScheme = ColorScheme {
+    name: "Moonbase",
+    border_color: [233, 239, 242],
+    background_color: [146, 148, 151],
+    empty_cell_color: [158, 161, 164],
+    triangle_outer_color: [183, 94, 94],
+    triangle_color: [170, 123, 123],
+    diamond_outer_color: [82, 69, 86],
+    diamond_color: [122, 117, 124],
+    square_outer_color: [83, 116, 165],
+    square_color: [115, 148, 175],
+    connector_color: [190, 214, 142],
+};
+
+const STONE_IN_FOCUS: ColorScheme = ColorScheme {
+    name: "Stone in Focus",
+    border_color: [232, 240, 222],
+    background_color: [116, 149, 153],
+    empty_cell_color: [132, 162, 163],
+    triangle_outer_color: [103, 103, 124],
+    triangle_color: [186, 190, 204],
+    diamond_outer_color: [175, 97, 44],
+    diamond_color: [216, 138, 85],
+    square_outer_color: [35, 31, 32],
+    square_color: [88, 89, 91],
+    connector_color: [186, 108, 123],
+};
+
+const VELVET_ICE: ColorScheme = ColorScheme {
+    name: "Velvet Ice",
+    border_color: [242, 237, 247],
+    background_color: [155, 97, 119],
+    empty_cell_color: [167, 117, 137],
+    triangle_outer_color: [0, 192, 237],
+    triangle_color: [61, 168, 196],
+    diamond_outer_color: [153, 55, 55],
+    diamond_color: [191, 64, 64],
+    square_outer_color: [56, 56, 56],
+    square_color: [20, 19, 19],
+    connector_color: [214, 135, 131],
+};
+
+const ELECTRO: ColorScheme = ColorScheme {
+    name: "Electro",
+    border_color: [235, 245, 247],
+    background_color: [20, 20, 20],
+    empty_cell_color: [50, 51, 52],
+    triangle_outer_color: [251, 0, 225],
+    triangle_color: [216, 158, 212],
+    diamond_outer_color: [81, 255, 0],
+    diamond_color: [30, 214, 30],
+    square_outer_color: [0, 249, 255],
+    square_color: [112, 232, 228],
+    connector_color: [251, 174, 23],
+};
+
+const TULIP: ColorScheme = ColorScheme {
+    name: "Tulip",
+    border_color: [238, 224, 248],
+    background_color: [151, 114, 177],
+    empty_cell_color: [164, 129, 187],
+    triangle_outer_color: [155, 12, 87],
+    triangle_color: [192, 81, 129],
+    diamond_outer_color: [108, 159, 61],
+    diamond_color: [149, 183, 137],
+    square_outer_color: [15, 120, 151],
+    square_color: [105, 151, 190],
+    connector_color: [222, 168, 202],
+};
+
+const TANGERINE: ColorScheme = ColorScheme {
+    name: "Tangerine",
+    border_color: [239, 232, 223],
+    background_color: [211, 144, 110],
+    empty_cell_color: [215, 156, 126],
+    triangle_outer_color: [181, 118, 163],
+    triangle_color: [221, 151, 205],
+    diamond_outer_color: [100, 107, 8],
+    diamond_color: [147, 150, 29],
+    square_outer_color: [40, 114, 188],
+    square_color: [38, 169, 224],
+    connector_color: [184, 200, 211],
+};
+
+const KIND_OF_BLUE: ColorScheme = ColorScheme {
+    name: "Kind of Blue",
+    border_color: [223, 232, 237],
+    background_color: [45, 162, 219],
+    empty_cell_color: [70, 172, 222],
+    triangle_outer_color: [87, 200, 232],
+    triangle_color: [157, 204, 215],
+    diamond_outer_color: [164, 148, 85],
+    diamond_color: [213, 191, 100],
+    square_outer_color: [59, 75, 158],
+    square_color: [78, 93, 169],
+    connector_color: [167, 219, 215],
+};
+
+const SMOKING_ROOM: ColorScheme = ColorScheme {
+    name: "Smoking Room",
+    border_color: [229, 221, 237],
+    background_color: [82, 69, 86],
+    empty_cell_color: [103, 90, 107],
+    triangle_outer_color: [254, 200, 23],
+    triangle_color: [198, 162, 60],
+    diamond_outer_color: [155, 102, 55],
+    diamond_color: [135, 79, 41],
+    square_outer_color: [120, 142, 158],
+    square_color: [84, 100, 109],
+    connector_color: [204, 185, 163],
+};
+
+const DESERT: ColorScheme = ColorScheme {
+    name: "Desert",
+    border_color: [239, 234, 223],
+    background_color: [196, 178, 148],
+    empty_cell_color: [202, 186, 158],
+    triangle_outer_color: [239, 187, 101],
+    triangle_color: [232, 159, 54],
+    diamond_outer_color: [160, 78, 68],
+    diamond_color: [181, 116, 111],
+    square_outer_color: [27, 117, 187],
+    square_color: [38, 169, 224],
+    connector_color: [86, 86, 86],
+};
+
+const PADDLEPOP: ColorScheme = ColorScheme {
+    name: "Paddlepop",
+    border_color: [247, 243, 238],
+    background_color: [214, 185, 135],
+    empty_cell_color: [219, 193, 149],
+    triangle_outer_color: [29, 219, 183],
+    triangle_color: [111, 109, 188],
+    diamond_outer_color: [244, 255, 31],
+    diamond_color: [107, 249, 107],
+    square_outer_color: [198, 23, 198],
+    square_color: [214, 50, 136],
+    connector_color: [188, 145, 81],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_border() {
+        let border_colors = border_colors();
+
+        assert_eq!(
+            match_border_color_in(&border_colors, &ORIGINAL.border_color, 0),
+            Some(ORIGINAL.border_color)
+        );
+        assert_eq!(
+            match_border_color_in(&border_colors, &[236, 244, 226], 0),
+            None
+        );
+
+        // Nearer to Original than to Stone in Focus, which is only a few units away
+        assert_eq!(
+            match_border_color_in(&border_colors, &[236, 244, 226], 6),
+            Some(ORIGINAL.border_color)
+        );
+        assert_eq!(
+            match_border_color_in(&border_colors, &[236, 244, 226], 5),
+            None
+        );
+        assert_eq!(
+            match_border_color_in(&border_colors, &[231, 239, 221], 2),
+            Some(STONE_IN_FOCUS.border_color)
+        );
+        assert_eq!(match_border_color_in(&border_colors, &[0, 0, 0], 100), None);
+        assert_eq!(
+            match_border_color(&[236, 244, 226], 6),
+            Some(ORIGINAL.border_color)
+        );
+    }
+}
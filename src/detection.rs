@@ -0,0 +1,976 @@
+use crate::{
+    Connectivity, Error,
+    bounds::Bounds,
+    color_scheme::{ColorScheme, color_distance},
+    edgemap::{EdgeMap, Solution},
+    grid::Grid,
+    image::{Image, View},
+    overlay::Overlay,
+    pixels::Pixels,
+    tile::{DetectOptions, Tile, TileType, check_terminal_count, check_terminal_counts},
+};
+
+// Maximum number of readings of the uncertain tiles that solve() tries
+const MAX_INTERPRETATIONS: usize = 64;
+
+// Segments with less than this fraction of the area of the largest segment, or with
+// less than this fraction of their pixels in their interior, are antialiasing
+// fringes along tile edges, where blended colors happen to match a class
+const MIN_AREA_FRACTION: usize = 16;
+const MIN_INTERIOR_FRACTION: usize = 4;
+
+// Fragments of a tile may be up to this fraction of the size of the largest tile
+// apart, and a tile merged from fragments may be larger than the largest tile by
+// this fraction of its size
+const MAX_GAP_FRACTION: u16 = 4;
+const MERGED_SLACK_FRACTION: u16 = 8;
+
+// Indexes into ColorScheme::shape_colors() of the colors of tile interiors, that is
+// intermediates, terminal cores and connectors
+const TILE_CLASSES: [usize; 4] = [1, 3, 5, 6];
+
+// Tile interiors fill at least this fraction of the square around them, and the
+// stripe of a tile color along the middle of a drawn path much less
+const MIN_FILL_FRACTION: usize = 5;
+
+// A drawn path's stripe is narrower than twice this fraction of the size of a tile,
+// so opening a segment by it leaves only the tiles attached to the stripe
+const STRIPE_RADIUS_FRACTION: u16 = 24;
+
+// A terminal core is surrounded by the terminal's outer color at least this fraction
+// of the core's size away, which the thin outline of a tile on a drawn path isn't
+const CORE_MARGIN_FRACTION: u16 = 6;
+const MAX_BLENDED_PIXELS: usize = 2;
+
+// A connector hole smaller than this fraction of the connector's largest hole may be
+// a notch that antialiasing closed off
+const MIN_HOLE_FRACTION: usize = 4;
+
+// The result of tile detection, with the details behind each tile and the
+// segments that didn't become tiles
+#[derive(Debug)]
+pub struct Detection {
+    pub tiles: Vec<DetectedTile>,
+    pub ignored: Vec<IgnoredSegment>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DetectedTile {
+    pub tile: Tile,
+
+    // Number of pixels of the segment the tile was detected from, and its extent
+    pub area: usize,
+    pub bounds: Bounds,
+
+    // Number of holes of connectors, 0 for other tiles
+    pub num_holes: usize,
+
+    // Other tile types that the tile may be, most likely first, if the detector
+    // wasn't sure.  Tiles with alternatives are the ones to double-check.
+    pub alternatives: Vec<TileType>,
+
+    // Whether something drawn over the tile hides part of it, so that its reading
+    // is a guess among the alternatives rather than the most likely one
+    pub occluded: bool,
+}
+
+// A reading of the detected tiles that has a solution
+#[derive(Debug)]
+pub struct Interpretation {
+    // The tiles in the same order as Detection::tiles, with any alternatives used
+    pub tiles: Vec<Tile>,
+
+    pub grid: Grid,
+    pub solution: Solution,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct IgnoredSegment {
+    // Index into ColorScheme::shape_colors() of the segment's color, for detectors
+    // that classify segments by color
+    pub class: Option<usize>,
+
+    pub bounds: Bounds,
+    pub area: usize,
+    pub reason: IgnoreReason,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IgnoreReason {
+    // An inner color segment within a terminal, which is part of the terminal
+    TerminalCore,
+
+    // A thin or small segment of blended pixels along the edge of a tile
+    Fringe,
+
+    // Part of a path drawn on a board in play, or the outline of a tile on one
+    DrawnPath,
+
+    // Something that isn't part of the board drawn over a tile of the given class,
+    // such as the mouse pointer
+    Occlusion,
+
+    // A segment with a number of holes that no tile has, such as a stray blob next
+    // to the board
+    UnexpectedHoleCount(usize),
+}
+
+impl Detection {
+    // Detects the tiles of an image whose color scheme is already known.  If an
+    // overlay is given, the color masks, segment bounding boxes, connector hole
+    // counts and tile centers are drawn onto it as detection goes, so that the
+    // overlay shows how far detection got even if it fails.  This is the
+    // implementation of ColorTableDetector.
+    pub(crate) fn detect<T: Image + ?Sized>(
+        image: &T,
+        color_scheme: &ColorScheme,
+        options: &DetectOptions,
+        mut overlay: Option<&mut Overlay>,
+    ) -> Result<Self, Error> {
+        if image.width() > u32::from(u16::MAX) || image.height() > u32::from(u16::MAX) {
+            return Err(Error::InvalidRegion(format!(
+                "Image dimensions {}x{} too large",
+                image.width(),
+                image.height()
+            )));
+        }
+
+        let shape_colors = color_scheme.shape_colors();
+
+        let mut masks: [Pixels; 7] =
+            std::array::from_fn(|_| Pixels::with_size(image.width(), image.height()));
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+
+                let pixel = [p.0, p.1, p.2];
+
+                let class = if options.color_tolerance == 0 {
+                    shape_colors.iter().position(|color| *color == pixel)
+                } else {
+                    color_scheme.nearest_shape_color(&pixel, options.color_tolerance)
+                };
+
+                if let Some(class) = class {
+                    masks[class].insert(x, y);
+                }
+            }
+        }
+
+        if let Some(overlay) = overlay.as_deref_mut() {
+            for (class, mask) in masks.iter().enumerate() {
+                overlay.mask(class, mask);
+            }
+        }
+
+        let mut ignored = Vec::new();
+
+        let mut segments = masks.map(|mut mask| mask.segment_all_with(options.connectivity));
+
+        // Tiles are all about the same size, so thresholds relative to the largest
+        // segment work at any scale.  Paths drawn on a board in play have the
+        // terminal colors and can be much larger, so only the colors of tile
+        // interiors count.
+        let reference_area = TILE_CLASSES
+            .iter()
+            .flat_map(|class| &segments[*class])
+            .map(Pixels::len)
+            .max()
+            .unwrap_or(0);
+
+        for (class, class_segments) in segments.iter_mut().enumerate() {
+            for segment in std::mem::take(class_segments) {
+                let reason = if is_fringe(&segment, reference_area) {
+                    IgnoreReason::Fringe
+                } else if TILE_CLASSES.contains(&class) && is_stripe(&segment) {
+                    // A drawn path's stripe runs into the tiles along it, so a stripe
+                    // may have tiles attached, which remain when it is opened away
+                    let radius = u16::try_from(reference_area.isqrt()).unwrap_or(u16::MAX)
+                        / STRIPE_RADIUS_FRACTION;
+
+                    for piece in segment
+                        .opened(radius)
+                        .segment_all_with(options.connectivity)
+                    {
+                        if !is_fringe(&piece, reference_area) && !is_stripe(&piece) {
+                            class_segments.push(piece);
+                        }
+                    }
+
+                    IgnoreReason::DrawnPath
+                } else {
+                    class_segments.push(segment);
+                    continue;
+                };
+
+                ignored.push(IgnoredSegment {
+                    class: Some(class),
+                    bounds: segment.bounds,
+                    area: segment.len(),
+                    reason,
+                });
+            }
+        }
+
+        let max_extent = TILE_CLASSES
+            .iter()
+            .flat_map(|class| &segments[*class])
+            .map(|segment| extent(&segment.bounds))
+            .max()
+            .unwrap_or(0);
+
+        let max_merged_extent = max_extent.saturating_add(max_extent / MERGED_SLACK_FRACTION);
+
+        for class_segments in &mut segments {
+            for segment in std::mem::take(class_segments) {
+                merge_into(class_segments, segment, max_extent);
+            }
+        }
+
+        if let Some(overlay) = overlay.as_deref_mut() {
+            for segment in segments.iter().flatten() {
+                overlay.bounds(&segment.bounds);
+            }
+        }
+
+        let [
+            triangle_outer,
+            triangle,
+            diamond_outer,
+            diamond,
+            square_outer,
+            square,
+            connector,
+        ] = segments;
+
+        let mut tiles = Vec::new();
+
+        for (shape, outer_class, outer, inner, terminal_type, intermediate_type) in [
+            (
+                "triangle",
+                0,
+                triangle_outer,
+                triangle,
+                TileType::TriangleTerminal,
+                TileType::Triangle,
+            ),
+            (
+                "diamond",
+                2,
+                diamond_outer,
+                diamond,
+                TileType::DiamondTerminal,
+                TileType::Diamond,
+            ),
+            (
+                "square",
+                4,
+                square_outer,
+                square,
+                TileType::SquareTerminal,
+                TileType::Square,
+            ),
+        ] {
+            // The outer color of a terminal on a drawn path gives way to the path's
+            // outline, but its core still has a center of another color
+            let (cores, intermediates): (Vec<_>, Vec<_>) = inner
+                .iter()
+                .map(|segment| (segment, enclosing_segment(segment, &outer)))
+                .partition(|(segment, enclosing)| {
+                    enclosing.is_some() || has_center_hole(segment, options.connectivity)
+                });
+
+            check_terminal_count(shape, cores.len())?;
+
+            for (segment, _) in intermediates {
+                tiles.push(DetectedTile::new(segment, intermediate_type, 0));
+            }
+
+            let mut used = vec![false; outer.len()];
+
+            for (core, enclosing) in cores {
+                // A path drawn from a terminal can join its outer segment to the tiles
+                // along the path, and then only the core tells where the terminal is
+                let segment = match enclosing {
+                    Some(index) if extent(&outer[index].bounds) <= max_merged_extent => {
+                        used[index] = true;
+
+                        &outer[index]
+                    }
+                    _ => core,
+                };
+
+                tiles.push(DetectedTile::new(segment, terminal_type, 0));
+
+                ignored.push(IgnoredSegment {
+                    class: Some(outer_class + 1),
+                    bounds: core.bounds,
+                    area: core.len(),
+                    reason: IgnoreReason::TerminalCore,
+                });
+            }
+
+            for (segment, _) in outer.iter().zip(used).filter(|(_, used)| !used) {
+                ignored.push(IgnoredSegment {
+                    class: Some(outer_class),
+                    bounds: segment.bounds,
+                    area: segment.len(),
+                    reason: IgnoreReason::DrawnPath,
+                });
+            }
+        }
+
+        for segment in &connector {
+            // A pointer or an overlay drawn over a connector can fill a hole, which
+            // then isn't the background showing through, or join holes together or
+            // to the outside
+            let occlusions = occlusions(image, segment, color_scheme, options);
+
+            let mut holes = segment.holes(options.connectivity);
+
+            holes.retain(|hole| !is_occluded(hole, &occlusions));
+
+            let num_holes = holes.len();
+
+            if let Some(overlay) = overlay.as_deref_mut() {
+                overlay.hole_count(&segment.bounds, num_holes);
+            }
+
+            // The holes that an occlusion hides can't be counted, so the visible
+            // ones only give a lower bound, and the connector may be any of the
+            // connectors with at least as many holes
+            let readings: Vec<_> = if occlusions.is_empty() {
+                connector_type(num_holes).into_iter().collect()
+            } else {
+                (num_holes.max(2)..=4)
+                    .rev()
+                    .filter_map(connector_type)
+                    .collect()
+            };
+
+            let tile_type = match readings.first() {
+                Some(tile_type) => *tile_type,
+                None => {
+                    return Err(Error::MalformedTileLayout(format!(
+                        "Connector at {:?} ({} pixels, Euler number {}) has {} hole(s) with {}, \
+                         expected 2 to 4",
+                        segment.bounds,
+                        segment.len(),
+                        segment.euler_number(options.connectivity),
+                        num_holes,
+                        options.connectivity
+                    )));
+                }
+            };
+
+            let mut detected = DetectedTile::new(segment, tile_type, num_holes);
+
+            detected.occluded = !occlusions.is_empty();
+
+            let largest = holes.iter().map(Pixels::len).max().unwrap_or(0);
+
+            // A very small hole may be a closed-off notch
+            let fewer = holes
+                .iter()
+                .any(|hole| hole.len() * MIN_HOLE_FRACTION < largest)
+                .then(|| num_holes - 1)
+                .and_then(connector_type);
+
+            for alternative in readings.into_iter().skip(1).chain(fewer) {
+                if alternative != tile_type && !detected.alternatives.contains(&alternative) {
+                    detected.alternatives.push(alternative);
+                }
+            }
+
+            for occlusion in &occlusions {
+                ignored.push(IgnoredSegment {
+                    class: Some(6),
+                    bounds: occlusion.bounds,
+                    area: occlusion.len(),
+                    reason: IgnoreReason::Occlusion,
+                });
+            }
+
+            tiles.push(detected);
+        }
+
+        if let Some(overlay) = overlay {
+            for detected in &tiles {
+                overlay.tile(&detected.tile);
+            }
+        }
+
+        tiles.sort_by_key(|detected| detected.tile);
+
+        Ok(Detection { tiles, ignored })
+    }
+
+    pub fn tiles(&self) -> Vec<Tile> {
+        self.tiles.iter().map(|detected| detected.tile).collect()
+    }
+
+    // Solves the board, trying the alternatives of uncertain tiles if the most
+    // likely reading doesn't work out.  Readings that replace fewer tiles are tried
+    // first.  If none has a solution, returns the error for the most likely one.
+    // Occluded tiles have no most likely reading, so with those every reading is
+    // tried, and readings that differ in an occluded tile can't both have a
+    // solution.
+    pub fn solve(&self) -> Result<Interpretation, Error> {
+        let occluded: Vec<_> = (0..self.tiles.len())
+            .filter(|index| {
+                let detected = &self.tiles[*index];
+
+                detected.occluded && !detected.alternatives.is_empty()
+            })
+            .collect();
+
+        let mut first_err = None;
+        let mut solved: Option<Interpretation> = None;
+
+        for substitution in self.substitutions() {
+            let mut tiles = self.tiles();
+
+            for (index, tile_type) in substitution {
+                tiles[index].tile_type = tile_type;
+            }
+
+            match Interpretation::new(tiles) {
+                Ok(interpretation) if occluded.is_empty() => return Ok(interpretation),
+                Ok(interpretation) => match &solved {
+                    None => solved = Some(interpretation),
+                    Some(other) => {
+                        if let Some(index) = occluded
+                            .iter()
+                            .find(|index| other.tiles[**index] != interpretation.tiles[**index])
+                        {
+                            return Err(Error::MalformedTileLayout(format!(
+                                "Occluded tile at {:?} can be read as either {:?} or {:?}",
+                                self.tiles[*index].bounds,
+                                other.tiles[*index].tile_type,
+                                interpretation.tiles[*index].tile_type
+                            )));
+                        }
+                    }
+                },
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        solved.ok_or_else(|| first_err.unwrap())
+    }
+
+    // Lists sets of (tile index, alternative) replacements, by increasing size and
+    // up to MAX_INTERPRETATIONS of them, starting with the empty set
+    fn substitutions(&self) -> Vec<Vec<(usize, TileType)>> {
+        let mut substitutions = vec![Vec::new()];
+        let mut level = 0..1;
+
+        while !level.is_empty() && substitutions.len() < MAX_INTERPRETATIONS {
+            for i in level.clone() {
+                // Only extend with tiles after the last replaced one, so that each
+                // set is listed once
+                let start = substitutions[i].last().map_or(0, |(index, _)| index + 1);
+
+                for (index, detected) in self.tiles.iter().enumerate().skip(start) {
+                    for tile_type in &detected.alternatives {
+                        if substitutions.len() == MAX_INTERPRETATIONS {
+                            return substitutions;
+                        }
+
+                        let mut substitution = substitutions[i].clone();
+
+                        substitution.push((index, *tile_type));
+
+                        substitutions.push(substitution);
+                    }
+                }
+            }
+
+            level = level.end..substitutions.len();
+        }
+
+        substitutions
+    }
+
+    // Maps a detection within a view to the coordinate space of the view's parent
+    pub fn to_parent<T: Image + ?Sized>(&self, view: &View<T>) -> Result<Self, Error> {
+        Ok(Detection {
+            tiles: self
+                .tiles
+                .iter()
+                .map(|detected| {
+                    Ok(DetectedTile {
+                        tile: detected.tile.to_parent(view)?,
+                        bounds: bounds_to_parent(&detected.bounds, view)?,
+                        ..detected.clone()
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+            ignored: self
+                .ignored
+                .iter()
+                .map(|ignored| {
+                    Ok(IgnoredSegment {
+                        bounds: bounds_to_parent(&ignored.bounds, view)?,
+                        ..*ignored
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+}
+
+impl DetectedTile {
+    pub(crate) fn new(segment: &Pixels, tile_type: TileType, num_holes: usize) -> Self {
+        DetectedTile {
+            tile: Tile::new(&segment.bounds, tile_type),
+            area: segment.len(),
+            bounds: segment.bounds,
+            num_holes,
+            alternatives: Vec::new(),
+            occluded: false,
+        }
+    }
+
+    // Distance in pixels between the point the tile is aligned to the grid by and
+    // the nearest cell center of the grid's lattice
+    pub fn grid_distance(&self, grid: &Grid) -> f64 {
+        let lattice = &grid.lattice;
+
+        let nearest = |position: u16, origin: f64, pitch: f64, count: usize| {
+            let position = f64::from(position);
+
+            let index = if pitch > 0.0 {
+                ((position - origin) / pitch)
+                    .round()
+                    .clamp(0.0, count.saturating_sub(1) as f64)
+            } else {
+                0.0
+            };
+
+            (position - (origin + index * pitch)).abs()
+        };
+
+        nearest(
+            self.tile.grid_x,
+            lattice.origin_x,
+            lattice.pitch_x,
+            grid.columns(),
+        )
+        .hypot(nearest(
+            self.tile.grid_y,
+            lattice.origin_y,
+            lattice.pitch_y,
+            grid.rows(),
+        ))
+    }
+}
+
+impl Interpretation {
+    fn new(tiles: Vec<Tile>) -> Result<Self, Error> {
+        check_terminal_counts(&tiles)?;
+
+        let grid = Grid::from_tiles(&tiles)?;
+
+        let solution = EdgeMap::solve(&grid)?;
+
+        Ok(Interpretation {
+            tiles,
+            grid,
+            solution,
+        })
+    }
+}
+
+// A notch that antialiasing opened up to the outside or closed off shows up as a
+// hole too few or too many.  Counts just outside the valid range are read as the
+// nearest connector, and the solver has the final say.
+fn connector_type(num_holes: usize) -> Option<TileType> {
+    match num_holes {
+        1 | 2 => Some(TileType::Connect2),
+        3 => Some(TileType::Connect3),
+        4 | 5 => Some(TileType::Connect4),
+        _ => None,
+    }
+}
+
+// Whether more than half of a hole is covered by occlusions
+fn is_occluded(hole: &Pixels, occlusions: &[Pixels]) -> bool {
+    let covered = hole
+        .iter()
+        .filter(|(x, y)| occlusions.iter().any(|blob| blob.contains(*x, *y)))
+        .count();
+
+    2 * covered > hole.len()
+}
+
+// Finds solid blobs within a segment's bounding box that are neither part of the
+// segment nor of the board around it.  Blended pixels along the segment's edges are
+// too thin to count.  A blob may go on past the bounding box, such as a bar drawn
+// across the segment, so its pixels count as interior by what is around them in the
+// image.
+fn occlusions<T: Image + ?Sized>(
+    image: &T,
+    segment: &Pixels,
+    color_scheme: &ColorScheme,
+    options: &DetectOptions,
+) -> Vec<Pixels> {
+    let bounds = segment.bounds;
+
+    // The background, and the paths, tile outlines and filled in connector holes of a
+    // board in play
+    let shape_colors = color_scheme.shape_colors();
+    let board_colors = [
+        color_scheme.background_color,
+        color_scheme.empty_cell_color,
+        color_scheme.border_color,
+        shape_colors[0],
+        shape_colors[1],
+        shape_colors[2],
+        shape_colors[3],
+        shape_colors[4],
+        shape_colors[5],
+    ];
+
+    let is_foreign = |x: u16, y: u16| {
+        u32::from(x) < image.width()
+            && u32::from(y) < image.height()
+            && !segment.contains(x, y)
+            && !has_color(image, x, y, &board_colors, options)
+    };
+
+    let mut foreign = Pixels::with_frame(
+        bounds.min_x,
+        bounds.min_y,
+        u32::from(bounds.max_x - bounds.min_x) + 1,
+        u32::from(bounds.max_y - bounds.min_y) + 1,
+    );
+
+    for x in bounds.min_x..=bounds.max_x {
+        for y in bounds.min_y..=bounds.max_y {
+            if is_foreign(x, y) {
+                foreign.insert(x, y);
+            }
+        }
+    }
+
+    foreign
+        .segment_all_with(options.connectivity)
+        .into_iter()
+        .filter(|blob| {
+            let interior = blob
+                .iter()
+                .filter(|(x, y)| {
+                    x.checked_sub(1).is_some_and(|x| is_foreign(x, *y))
+                        && x.checked_add(1).is_some_and(|x| is_foreign(x, *y))
+                        && y.checked_sub(1).is_some_and(|y| is_foreign(*x, y))
+                        && y.checked_add(1).is_some_and(|y| is_foreign(*x, y))
+                })
+                .count();
+
+            2 * interior >= blob.len()
+        })
+        .collect()
+}
+
+fn has_color<T: Image + ?Sized>(
+    image: &T,
+    x: u16,
+    y: u16,
+    colors: &[[u8; 3]],
+    options: &DetectOptions,
+) -> bool {
+    let p = image.pixel(u32::from(x), u32::from(y));
+
+    colors.iter().any(|color| {
+        color_distance(&[p.0, p.1, p.2], color) <= options.color_tolerance.saturating_pow(2)
+    })
+}
+
+// Finds the segment of a terminal's outer color that surrounds an inner color
+// segment, if it is the terminal's core.  Looks outward from the segment's center in
+// the four axis directions, to a little past the segment's outer edge.
+fn enclosing_segment(segment: &Pixels, outer: &[Pixels]) -> Option<usize> {
+    let bounds = &segment.bounds;
+    let margin = (extent(bounds) / CORE_MARGIN_FRACTION).max(1);
+    let (center_x, center_y) = (
+        bounds.min_x + (bounds.max_x - bounds.min_x) / 2,
+        bounds.min_y + (bounds.max_y - bounds.min_y) / 2,
+    );
+
+    let mut enclosing = None;
+
+    for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)] {
+        let step = |x: u16, y: u16, distance: u16| {
+            Some((
+                x.checked_add_signed(dx * i16::try_from(distance).ok()?)?,
+                y.checked_add_signed(dy * i16::try_from(distance).ok()?)?,
+            ))
+        };
+
+        // Cores of terminals that have a center of another color are rings, so go by
+        // the segment's outermost pixel in each direction
+        let (mut x, mut y) = (center_x, center_y);
+        let mut edge = None;
+
+        while (bounds.min_x..=bounds.max_x).contains(&x)
+            && (bounds.min_y..=bounds.max_y).contains(&y)
+        {
+            if segment.contains(x, y) {
+                edge = Some((x, y));
+            }
+
+            (x, y) = step(x, y, 1)?;
+        }
+
+        let (x, y) = edge?;
+        let (mut x, mut y) = step(x, y, 1)?;
+
+        // Blended pixels along the edge belong to neither segment
+        for _ in 0..MAX_BLENDED_PIXELS {
+            if outer.iter().any(|other| other.contains(x, y)) {
+                break;
+            }
+
+            (x, y) = step(x, y, 1)?;
+        }
+
+        let (x, y) = step(x, y, margin - 1)?;
+
+        let index = outer.iter().position(|other| other.contains(x, y))?;
+
+        if *enclosing.get_or_insert(index) != index {
+            return None;
+        }
+    }
+
+    enclosing
+}
+
+// Whether the center of a segment's bounding box is in one of its holes
+fn has_center_hole(segment: &Pixels, connectivity: Connectivity) -> bool {
+    let bounds = &segment.bounds;
+    let (center_x, center_y) = (
+        bounds.min_x + (bounds.max_x - bounds.min_x) / 2,
+        bounds.min_y + (bounds.max_y - bounds.min_y) / 2,
+    );
+
+    segment
+        .holes(connectivity)
+        .iter()
+        .any(|hole| hole.contains(center_x, center_y))
+}
+
+// Whether a segment is too small or too thin to be a tile or a terminal core
+pub(crate) fn is_fringe(segment: &Pixels, reference_area: usize) -> bool {
+    segment.len() * MIN_AREA_FRACTION < reference_area
+        || interior(segment) * MIN_INTERIOR_FRACTION < segment.len()
+}
+
+// Whether a segment is long and thin, like a line rather than a tile
+fn is_stripe(segment: &Pixels) -> bool {
+    let extent = usize::from(extent(&segment.bounds));
+
+    segment.len() * MIN_FILL_FRACTION < extent * extent
+}
+
+// Counts the pixels whose four neighbors are all in the set as well
+fn interior(pixels: &Pixels) -> usize {
+    pixels
+        .iter()
+        .filter(|(x, y)| {
+            x.checked_sub(1).is_some_and(|x| pixels.contains(x, *y))
+                && x.checked_add(1).is_some_and(|x| pixels.contains(x, *y))
+                && y.checked_sub(1).is_some_and(|y| pixels.contains(*x, y))
+                && y.checked_add(1).is_some_and(|y| pixels.contains(*x, y))
+        })
+        .count()
+}
+
+// Adds a segment to a list of segments of the same class, merging it with any that
+// it's a fragment of the same tile with.  Blended pixels across a thin part of a
+// tile, or the mouse pointer drawn across one, can split it into fragments, which
+// are close together and fit within the size of a tile when merged.
+fn merge_into(segments: &mut Vec<Pixels>, mut segment: Pixels, max_extent: u16) {
+    let max_gap = max_extent / MAX_GAP_FRACTION;
+    let max_merged_extent = max_extent.saturating_add(max_extent / MERGED_SLACK_FRACTION);
+
+    while let Some(i) = segments.iter().position(|other| {
+        let (a, b) = (&other.bounds, &segment.bounds);
+
+        let gap_x = a
+            .min_x
+            .saturating_sub(b.max_x)
+            .max(b.min_x.saturating_sub(a.max_x));
+        let gap_y = a
+            .min_y
+            .saturating_sub(b.max_y)
+            .max(b.min_y.saturating_sub(a.max_y));
+
+        gap_x.max(gap_y) <= max_gap && extent(&a.union(b)) <= max_merged_extent
+    }) {
+        for (x, y) in segments.swap_remove(i).iter() {
+            segment.insert(x, y);
+        }
+    }
+
+    segments.push(segment);
+}
+
+// The larger of the width and height of a bounding box
+pub(crate) fn extent(bounds: &Bounds) -> u16 {
+    (bounds.max_x - bounds.min_x).max(bounds.max_y - bounds.min_y) + 1
+}
+
+fn bounds_to_parent<T: Image + ?Sized>(bounds: &Bounds, view: &View<T>) -> Result<Bounds, Error> {
+    let (min_x, min_y) = view.to_parent(u32::from(bounds.min_x), u32::from(bounds.min_y));
+    let (max_x, max_y) = view.to_parent(u32::from(bounds.max_x), u32::from(bounds.max_y));
+
+    let (Ok(min_x), Ok(max_x), Ok(min_y), Ok(max_y)) = (
+        u16::try_from(min_x),
+        u16::try_from(max_x),
+        u16::try_from(min_y),
+        u16::try_from(max_y),
+    ) else {
+        return Err(Error::InvalidRegion(format!(
+            "Bounds {:?} out of range",
+            bounds
+        )));
+    };
+
+    Ok(Bounds {
+        min_x,
+        max_x,
+        min_y,
+        max_y,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::Board,
+        detector::ColorTableDetector,
+        image::{self, UNDITHERED_COLOR_TOLERANCE},
+    };
+
+    // The first frame of a dithered GIF recording of a level, with black boxes drawn
+    // over it
+    struct Occluded {
+        image: Box<dyn Image>,
+        boxes: Vec<(u32, u32, u32, u32)>,
+    }
+
+    impl Occluded {
+        fn new(boxes: &[(u32, u32, u32, u32)]) -> Self {
+            let frame = image::open_frames(concat!(env!("CARGO_MANIFEST_DIR"), "/z23.gif"))
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+
+            Occluded {
+                image: image::undither(&*frame),
+                boxes: boxes.to_vec(),
+            }
+        }
+
+        fn detect(&self) -> Detection {
+            let options = DetectOptions {
+                color_tolerance: UNDITHERED_COLOR_TOLERANCE,
+                ..Default::default()
+            };
+
+            let board = Board::locate(self, &options).unwrap();
+            let color_scheme = board.detect_color_scheme(self, &options).unwrap();
+            let detector = ColorTableDetector {
+                color_scheme: Some(&color_scheme),
+                options,
+            };
+
+            board.detect(self, &detector, None).unwrap()
+        }
+    }
+
+    impl Image for Occluded {
+        fn width(&self) -> u32 {
+            self.image.width()
+        }
+
+        fn height(&self) -> u32 {
+            self.image.height()
+        }
+
+        fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+            let covered = self.boxes.iter().any(|(box_x, box_y, width, height)| {
+                (*box_x..box_x + width).contains(&x) && (*box_y..box_y + height).contains(&y)
+            });
+
+            if covered {
+                (0, 0, 0)
+            } else {
+                self.image.pixel(x, y)
+            }
+        }
+    }
+
+    // The detected tile whose bounding box contains a point
+    fn tile_at(detection: &Detection, x: u16, y: u16) -> &DetectedTile {
+        detection
+            .tiles
+            .iter()
+            .find(|detected| {
+                let bounds = &detected.bounds;
+
+                (bounds.min_x..=bounds.max_x).contains(&x)
+                    && (bounds.min_y..=bounds.max_y).contains(&y)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn occluded_connector() {
+        // A box over the center of the 2-connector in the top row hides its holes,
+        // and only one reading has a solution
+        let detection = Occluded::new(&[(786, 185, 14, 22)]).detect();
+        let detected = tile_at(&detection, 793, 196);
+
+        assert!(detected.occluded);
+        assert!(!detected.alternatives.is_empty());
+        assert_eq!(
+            detection.solve().unwrap().grid.level_key(),
+            "t2tdds2S-223tdd2S-T3D4D3ss-ttdTdsss"
+        );
+    }
+
+    #[test]
+    fn ambiguous_occluded_connector() {
+        // With the 4-connector in the middle of the board hidden, the board has a
+        // solution with a 3-connector there as well
+        for boxes in [[(1071, 469, 14, 22)], [(1023, 478, 110, 4)]] {
+            let detection = Occluded::new(&boxes).detect();
+            let detected = tile_at(&detection, 1078, 480);
+
+            assert!(detected.occluded);
+            assert!(detected.alternatives.contains(&TileType::Connect3));
+            assert!(matches!(
+                detection.solve(),
+                Err(Error::MalformedTileLayout(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn unoccluded_connector() {
+        let detection = Occluded::new(&[]).detect();
+        let detected = tile_at(&detection, 1078, 480);
+
+        assert!(!detected.occluded);
+        assert_eq!(detected.tile.tile_type, TileType::Connect4);
+        assert!(detected.alternatives.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use std::borrow::Cow;
+
+use crate::{
+    Error,
+    color_scheme::ColorScheme,
+    detection::Detection,
+    detector::TileDetector,
+    image::Image,
+    overlay::Overlay,
+    tile::{DetectOptions, Tile},
+};
+
+// Detects tiles by the colors of a known color scheme, which is determined from the
+// border color.  This is fast and exact, but needs the theme to be known (or
+// inferable).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColorTableDetector<'a> {
+    // The color scheme, if it was already detected, such as to print it
+    pub color_scheme: Option<&'a ColorScheme>,
+
+    pub options: DetectOptions,
+}
+
+impl TileDetector for ColorTableDetector<'_> {
+    fn detect(&self, image: &dyn Image, overlay: Option<&mut Overlay>) -> Result<Detection, Error> {
+        let color_scheme = match self.color_scheme {
+            Some(color_scheme) => Cow::Borrowed(color_scheme),
+            None => Tile::detect_color_scheme(image, &self.options)?,
+        };
+
+        Detection::detect(image, &color_scheme, &self.options, overlay)
+    }
+}
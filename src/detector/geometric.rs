@@ -0,0 +1,373 @@
+use std::{collections::BTreeMap, slice};
+
+use crate::{
+    Connectivity, Error,
+    color_scheme::color_distance,
+    detection::{DetectedTile, Detection, IgnoreReason, IgnoredSegment, extent},
+    detector::TileDetector,
+    image::Image,
+    overlay::Overlay,
+    pixels::Pixels,
+    shape::{Shape, classify_shape},
+    tile::{Tile, TileType},
+};
+
+// Segments smaller than this, or than this fraction of the area of the largest
+// segment, are considered noise
+const MIN_SEGMENT_PIXELS: usize = 16;
+const MIN_AREA_FRACTION: usize = 16;
+
+// A terminal's core covers the middle of the tile out to at least this fraction of
+// the tile's size
+const CORE_RADIUS_FRACTION: u16 = 16;
+
+// Detects tiles by their geometry alone, so that it works with any color scheme
+// and with grayscale images:
+//
+// * Everything that stands out from the background (the most common color) by at
+//   least min_contrast is foreground.
+// * Foreground segments with 2, 3 or 4 enclosed holes are connectors.
+// * Other foreground segments are triangles, diamonds or squares by their outline,
+//   and terminals if their center differs in color from the rest of the tile (or
+//   is not foreground at all).  When the difference is close to the threshold, the
+//   other reading is offered as an alternative for the solver to try.  A segment
+//   within the hole of a terminal's outline is that terminal's core.
+//
+// Segments touching the edge of the image or spanning half of it are taken to be
+// part of the board frame and ignored, and so are segments with more holes than any
+// tile has and segments much smaller than the largest one.
+#[derive(Clone, Copy, Debug)]
+pub struct ShapeDetector {
+    // Minimum RGB distance from the background color for a pixel to be foreground.
+    // Tiles themselves are flat, so half of this distance from a tile's dominant
+    // color is enough for the tile's center to count as a terminal core.
+    pub min_contrast: u32,
+}
+
+impl Default for ShapeDetector {
+    fn default() -> Self {
+        ShapeDetector { min_contrast: 24 }
+    }
+}
+
+impl TileDetector for ShapeDetector {
+    fn detect(&self, image: &dyn Image, overlay: Option<&mut Overlay>) -> Result<Detection, Error> {
+        if image.width() > u32::from(u16::MAX) || image.height() > u32::from(u16::MAX) {
+            return Err(Error::InvalidRegion(format!(
+                "Image dimensions {}x{} too large",
+                image.width(),
+                image.height()
+            )));
+        }
+
+        let mut histogram: BTreeMap<[u8; 3], usize> = BTreeMap::new();
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+
+                *histogram.entry([p.0, p.1, p.2]).or_default() += 1;
+            }
+        }
+
+        let Some((background_color, _)) = histogram.iter().max_by_key(|(_, count)| **count) else {
+            return Err(Error::BoardNotFound);
+        };
+
+        let min_distance = self.min_contrast.saturating_pow(2);
+        let min_core_distance = (self.min_contrast / 2).saturating_pow(2);
+
+        let mut foreground = Pixels::with_size(image.width(), image.height());
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+
+                if color_distance(&[p.0, p.1, p.2], background_color) >= min_distance {
+                    foreground.insert(x, y);
+                }
+            }
+        }
+
+        let segments: Vec<_> = foreground
+            .segment_all()
+            .into_iter()
+            .filter(|segment| {
+                let bounds = segment.bounds;
+
+                segment.len() >= MIN_SEGMENT_PIXELS
+                    && bounds.min_x != 0
+                    && bounds.min_y != 0
+                    && u32::from(bounds.max_x) + 1 != image.width()
+                    && u32::from(bounds.max_y) + 1 != image.height()
+                    && 2 * u32::from(bounds.max_x - bounds.min_x) < image.width()
+                    && 2 * u32::from(bounds.max_y - bounds.min_y) < image.height()
+            })
+            .collect();
+
+        // Tiles are all about the same size, so much smaller segments are noise too,
+        // such as text around the board
+        let reference_area = segments.iter().map(Pixels::len).max().unwrap_or(0);
+
+        let segments: Vec<_> = segments
+            .into_iter()
+            .filter(|segment| segment.len() * MIN_AREA_FRACTION >= reference_area)
+            .map(|segment| {
+                let holes = segment.holes(Connectivity::Four);
+
+                (segment, holes)
+            })
+            .collect();
+
+        let mut tiles = Vec::new();
+        let mut ignored = Vec::new();
+
+        for (index, (segment, holes)) in segments.iter().enumerate() {
+            let bounds = segment.bounds;
+
+            // The core of a terminal that differs from the background, within the
+            // hole of its outline, is part of the terminal
+            let (x, y) = segment.iter().next().unwrap();
+
+            if segments.iter().enumerate().any(|(other, (_, holes))| {
+                other != index && holes.len() == 1 && holes[0].contains(x, y)
+            }) {
+                ignored.push(IgnoredSegment {
+                    class: None,
+                    bounds,
+                    area: segment.len(),
+                    reason: IgnoreReason::TerminalCore,
+                });
+
+                continue;
+            }
+
+            let num_holes = holes.len();
+
+            let mut alternative = None;
+
+            let tile_type = match num_holes {
+                // A terminal whose core blends in with the background has a hole
+                num_holes @ (0 | 1) => {
+                    let shape = classify_shape(slice::from_ref(segment));
+
+                    let (terminal, intermediate) = match shape {
+                        Shape::Triangle => (TileType::TriangleTerminal, TileType::Triangle),
+                        Shape::Diamond => (TileType::DiamondTerminal, TileType::Diamond),
+                        Shape::Square => (TileType::SquareTerminal, TileType::Square),
+                    };
+
+                    let center = Tile::new(&bounds, intermediate);
+
+                    // The middle of the tile rather than a single pixel, which may be
+                    // a stray one along a blended edge
+                    let radius = extent(&bounds) / CORE_RADIUS_FRACTION;
+
+                    let core = (center.grid_x.saturating_sub(radius)..=center.grid_x + radius)
+                        .flat_map(|x| {
+                            (center.grid_y.saturating_sub(radius)..=center.grid_y + radius)
+                                .map(move |y| (x, y))
+                        });
+
+                    let core_distance = color_distance(
+                        &dominant_color(image, core),
+                        &dominant_color(image, segment.iter()),
+                    );
+
+                    let (tile_type, other) = if num_holes == 1 || core_distance >= min_core_distance
+                    {
+                        (terminal, intermediate)
+                    } else {
+                        (intermediate, terminal)
+                    };
+
+                    // Within a factor of two of the threshold either way (the
+                    // distances are squared)
+                    if num_holes == 0
+                        && core_distance >= min_core_distance / 4
+                        && core_distance < min_core_distance.saturating_mul(4)
+                    {
+                        alternative = Some(other);
+                    }
+
+                    tile_type
+                }
+                2 => TileType::Connect2,
+                3 => TileType::Connect3,
+                4 => TileType::Connect4,
+                num_holes => {
+                    ignored.push(IgnoredSegment {
+                        class: None,
+                        bounds,
+                        area: segment.len(),
+                        reason: IgnoreReason::UnexpectedHoleCount(num_holes),
+                    });
+
+                    continue;
+                }
+            };
+
+            let mut detected = DetectedTile::new(segment, tile_type, num_holes);
+
+            detected.alternatives.extend(alternative);
+
+            tiles.push(detected);
+        }
+
+        if let Some(overlay) = overlay {
+            for detected in &tiles {
+                overlay.tile(&detected.tile);
+            }
+        }
+
+        tiles.sort_by_key(|detected| detected.tile);
+
+        Ok(Detection { tiles, ignored })
+    }
+}
+
+fn dominant_color(image: &dyn Image, pixels: impl Iterator<Item = (u16, u16)>) -> [u8; 3] {
+    let mut histogram: BTreeMap<[u8; 3], usize> = BTreeMap::new();
+
+    for (x, y) in pixels {
+        let p = image.pixel(u32::from(x), u32::from(y));
+
+        *histogram.entry([p.0, p.1, p.2]).or_default() += 1;
+    }
+
+    histogram
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(color, _)| color)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color_scheme::ColorScheme, image};
+
+    // A gray image with a black square that has five small holes in it
+    struct Blob;
+
+    impl Image for Blob {
+        fn width(&self) -> u32 {
+            64
+        }
+
+        fn height(&self) -> u32 {
+            64
+        }
+
+        fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+            let in_square = (16..40).contains(&x) && (16..40).contains(&y);
+            let in_hole = y == 28 && (18..38).contains(&x) && x % 4 == 2;
+
+            if in_square && !in_hole {
+                (0, 0, 0)
+            } else {
+                (128, 128, 128)
+            }
+        }
+    }
+
+    #[test]
+    fn ignore_unexpected_hole_count() {
+        let detection = ShapeDetector::default().detect(&Blob, None).unwrap();
+
+        assert!(detection.tiles.is_empty());
+        assert_eq!(detection.ignored.len(), 1);
+        assert_eq!(
+            detection.ignored[0].reason,
+            IgnoreReason::UnexpectedHoleCount(5)
+        );
+    }
+
+    // The first frame of a dithered GIF recording of a level, with its dither
+    // averaged out and each pixel set to the nearest color of its color scheme, as
+    // in a screenshot, or to that color's luma
+    struct Quantized {
+        image: Box<dyn Image>,
+        palette: Vec<[u8; 3]>,
+        grayscale: bool,
+    }
+
+    impl Quantized {
+        fn new(grayscale: bool) -> Self {
+            let frame = image::open_frames(concat!(env!("CARGO_MANIFEST_DIR"), "/z23.gif"))
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+
+            let color_scheme = ColorScheme::by_name("Original").unwrap();
+
+            let mut palette = vec![
+                color_scheme.border_color,
+                color_scheme.background_color,
+                color_scheme.empty_cell_color,
+            ];
+
+            palette.extend(color_scheme.shape_colors());
+
+            Quantized {
+                image: image::undither(&*frame),
+                palette,
+                grayscale,
+            }
+        }
+    }
+
+    impl Image for Quantized {
+        fn width(&self) -> u32 {
+            self.image.width()
+        }
+
+        fn height(&self) -> u32 {
+            self.image.height()
+        }
+
+        fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+            let p = self.image.pixel(x, y);
+
+            let [r, g, b] = *self
+                .palette
+                .iter()
+                .min_by_key(|color| color_distance(color, &[p.0, p.1, p.2]))
+                .unwrap();
+
+            if self.grayscale {
+                let luma =
+                    ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000) as u8;
+
+                (luma, luma, luma)
+            } else {
+                (r, g, b)
+            }
+        }
+    }
+
+    #[test]
+    fn read_board() {
+        for grayscale in [false, true] {
+            let detection = ShapeDetector::default()
+                .detect(&Quantized::new(grayscale), None)
+                .unwrap();
+
+            // The cores of the diamond terminals are separate segments within their
+            // outlines
+            assert_eq!(
+                detection
+                    .ignored
+                    .iter()
+                    .filter(|ignored| ignored.reason == IgnoreReason::TerminalCore)
+                    .count(),
+                2
+            );
+            assert_eq!(
+                detection.solve().unwrap().grid.level_key(),
+                "t2tdds2S-223tdd2S-T3D4D3ss-ttdTdsss"
+            );
+        }
+    }
+}
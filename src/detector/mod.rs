@@ -0,0 +1,15 @@
+mod color_table;
+mod geometric;
+
+pub use color_table::ColorTableDetector;
+pub use geometric::ShapeDetector;
+
+use crate::{Error, detection::Detection, image::Image, overlay::Overlay};
+
+// A strategy for finding the tiles of a LYNE board in an image that contains just
+// the game area.  Board::detect runs a detector on the board within a larger image.
+pub trait TileDetector {
+    // Detects the tiles, with the details behind each tile.  If an overlay is given,
+    // detectors that can show how detection went draw onto it.
+    fn detect(&self, image: &dyn Image, overlay: Option<&mut Overlay>) -> Result<Detection, Error>;
+}
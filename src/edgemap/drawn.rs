@@ -0,0 +1,196 @@
+use crate::{
+    color_scheme::{ColorScheme, color_distance},
+    edgemap::{EdgeMap, PathType},
+    grid::Grid,
+    image::Image,
+    tile::{DetectOptions, TileType},
+};
+
+// Fractions of the way between two cell centers at which edges are sampled.  Tiles
+// cover the ends of an edge, and diagonal edges are sampled away from the point
+// where they cross the other diagonal between the same four cells.
+const STRAIGHT_SAMPLES: [f64; 3] = [0.45, 0.5, 0.55];
+const DIAGONAL_SAMPLES: [f64; 2] = [0.4, 0.6];
+
+// Samples look across a path up to this fraction of the distance between cell
+// centers to either side, which covers a path's outlines even where it is drawn a
+// little off the line between the cell centers
+const CROSS_SECTION_FRACTION: f64 = 8.0;
+
+impl EdgeMap {
+    // Finds the paths already drawn on a board in play.  LYNE draws a path as a line
+    // between the centers of the tiles it connects, in one of the colors of its
+    // shape's tiles, possibly with an outline in the border color.
+    pub fn detect_drawn<T: Image + ?Sized>(
+        image: &T,
+        grid: &Grid,
+        color_scheme: &ColorScheme,
+        options: &DetectOptions,
+    ) -> EdgeMap {
+        let mut edgemap = EdgeMap::empty(grid);
+
+        let path_type = |from: (usize, usize), to: (usize, usize), samples: &[f64]| {
+            if grid.cells[from.0][from.1] == TileType::Empty
+                || grid.cells[to.0][to.1] == TileType::Empty
+            {
+                return None;
+            }
+
+            let (from_x, from_y) = grid.center(from.0, from.1);
+            let (to_x, to_y) = grid.center(to.0, to.1);
+
+            let (dx, dy) = (to_x - from_x, to_y - from_y);
+            let length = dx.hypot(dy);
+            let (normal_x, normal_y) = (-dy / length, dx / length);
+
+            let half_width = (grid.lattice.pitch_x.min(grid.lattice.pitch_y)
+                / CROSS_SECTION_FRACTION)
+                .round() as i64;
+
+            let mut path_type = None;
+
+            for t in samples {
+                let (x, y) = (from_x + t * dx, from_y + t * dy);
+
+                // The pixels across the path, and whether each is part of its outline
+                let mut cross_section = Vec::new();
+
+                for offset in -half_width..=half_width {
+                    let offset = offset as f64;
+
+                    let (x, y) = (
+                        (x + offset * normal_x).round(),
+                        (y + offset * normal_y).round(),
+                    );
+
+                    if x < 0.0
+                        || y < 0.0
+                        || x >= f64::from(image.width())
+                        || y >= f64::from(image.height())
+                    {
+                        return None;
+                    }
+
+                    let p = image.pixel(x as u32, y as u32);
+                    let pixel = [p.0, p.1, p.2];
+
+                    let outline = color_distance(&pixel, &color_scheme.border_color)
+                        <= options.color_tolerance.saturating_pow(2);
+
+                    cross_section.push((pixel, outline));
+                }
+
+                // A path has its outline on both sides, with the stripe in the middle,
+                // possibly with background around it.  Blends of the outline and the
+                // background can look like a light stripe, so only the pixels midway
+                // between the outlines count, and the one least like the background
+                // is the stripe.  A path without an outline is all stripe.
+                let outlined = cross_section.iter().any(|(_, outline)| *outline);
+
+                let (gap_start, gap_end) = if outlined {
+                    let first = cross_section
+                        .iter()
+                        .position(|(_, outline)| *outline)
+                        .unwrap();
+                    let last = cross_section
+                        .iter()
+                        .rposition(|(_, outline)| *outline)
+                        .unwrap();
+
+                    (
+                        first
+                            + cross_section[first..]
+                                .iter()
+                                .take_while(|(_, outline)| *outline)
+                                .count(),
+                        last - cross_section[..last]
+                            .iter()
+                            .rev()
+                            .take_while(|(_, outline)| *outline)
+                            .count(),
+                    )
+                } else {
+                    (0, cross_section.len())
+                };
+
+                if gap_start >= gap_end {
+                    continue;
+                }
+
+                let middle = (gap_start + gap_end - 1) / 2;
+
+                let stripe = cross_section
+                    [middle.saturating_sub(1).max(gap_start)..=(middle + 1).min(gap_end - 1)]
+                    .iter()
+                    .filter(|(_, outline)| !*outline)
+                    .map(|(pixel, _)| pixel)
+                    .max_by_key(|pixel| color_distance(pixel, &color_scheme.background_color));
+
+                // A path without a stripe only tells that there is one
+                let stripe = match stripe {
+                    Some(stripe)
+                        if color_distance(stripe, &color_scheme.background_color)
+                            > options.color_tolerance.saturating_pow(2) =>
+                    {
+                        stripe
+                    }
+                    _ => continue,
+                };
+
+                // Paths have the colors of their shape's tiles, and never the
+                // connector color.  Blending between the outlines leaves the stripe
+                // of an outlined path only nearest to its color.
+                let tolerance = if outlined {
+                    u32::MAX
+                } else {
+                    options.color_tolerance.saturating_pow(2)
+                };
+
+                let sample_type = match color_scheme.shape_colors()[..6]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, color)| (i, color_distance(color, stripe)))
+                    .filter(|(_, distance)| *distance <= tolerance)
+                    .min_by_key(|(_, distance)| *distance)
+                {
+                    Some((0 | 1, _)) => PathType::Triangle,
+                    Some((2 | 3, _)) => PathType::Diamond,
+                    Some(_) => PathType::Square,
+                    None => return None,
+                };
+
+                if *path_type.get_or_insert(sample_type) != sample_type {
+                    return None;
+                }
+            }
+
+            path_type
+        };
+
+        for row in 0..grid.rows() {
+            for column in 0..grid.columns() {
+                let edges = &mut edgemap.cells[row][column];
+
+                if column + 1 < grid.columns() {
+                    edges.r = path_type((row, column), (row, column + 1), &STRAIGHT_SAMPLES);
+                }
+
+                if row + 1 < grid.rows() {
+                    edges.d = path_type((row, column), (row + 1, column), &STRAIGHT_SAMPLES);
+
+                    if column + 1 < grid.columns() {
+                        edges.dr =
+                            path_type((row, column), (row + 1, column + 1), &DIAGONAL_SAMPLES);
+                    }
+
+                    if column > 0 {
+                        edges.dl =
+                            path_type((row, column), (row + 1, column - 1), &DIAGONAL_SAMPLES);
+                    }
+                }
+            }
+        }
+
+        edgemap
+    }
+}
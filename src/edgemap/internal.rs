@@ -1,7 +1,5 @@
-use std::iter;
-
 use crate::{
-    edgemap::{EdgeMap, OutgoingEdges, PathType},
+    edgemap::{EdgeMap, PathType},
     grid::Grid,
     tile::TileType,
 };
@@ -23,22 +21,7 @@ impl<'a> EdgeMapInternal<'a> {
     fn new(grid: &'a Grid) -> Self {
         Self {
             grid,
-            edgemap: EdgeMap {
-                cells: iter::repeat_with(|| {
-                    iter::repeat_n(
-                        OutgoingEdges {
-                            r: None,
-                            dl: None,
-                            d: None,
-                            dr: None,
-                        },
-                        grid.columns(),
-                    )
-                    .collect::<Vec<_>>()
-                })
-                .take(grid.rows())
-                .collect::<Vec<_>>(),
-            },
+            edgemap: EdgeMap::empty(grid),
         }
     }
 
@@ -1,8 +1,11 @@
+mod drawn;
 mod internal;
 mod printable;
+mod solution;
 mod trail;
 
 use internal::EdgeMapInternal;
+pub use solution::Solution;
 
 use crate::grid::Grid;
 
@@ -34,4 +37,50 @@ impl EdgeMap {
     {
         EdgeMapInternal::find(grid, &mut got_solution)
     }
+
+    // An edge map of the grid's size without any edges
+    pub fn empty(grid: &Grid) -> Self {
+        EdgeMap {
+            cells: vec![
+                vec![
+                    OutgoingEdges {
+                        r: None,
+                        dl: None,
+                        d: None,
+                        dr: None,
+                    };
+                    grid.columns()
+                ];
+                grid.rows()
+            ],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells
+            .iter()
+            .flatten()
+            .all(|edges| edges.iter().all(Option::is_none))
+    }
+
+    // Whether every edge of this edge map is in the other one too, with the same path
+    // type
+    pub fn is_subset_of(&self, other: &EdgeMap) -> bool {
+        self.cells
+            .iter()
+            .flatten()
+            .zip(other.cells.iter().flatten())
+            .all(|(edges, other_edges)| {
+                edges
+                    .iter()
+                    .zip(other_edges.iter())
+                    .all(|(edge, other_edge)| edge.is_none() || edge == other_edge)
+            })
+    }
+}
+
+impl OutgoingEdges {
+    fn iter(&self) -> impl Iterator<Item = &Option<PathType>> {
+        [&self.r, &self.dl, &self.d, &self.dr].into_iter()
+    }
 }
@@ -0,0 +1,63 @@
+use crate::{
+    Error,
+    edgemap::{EdgeMap, PathType},
+    grid::Grid,
+};
+
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub edgemap: EdgeMap,
+    pub trail_triangle: Vec<(usize, usize)>,
+    pub trail_diamond: Vec<(usize, usize)>,
+    pub trail_square: Vec<(usize, usize)>,
+}
+
+impl Solution {
+    pub fn trails(&self) -> [&[(usize, usize)]; 3] {
+        [
+            &self.trail_triangle,
+            &self.trail_diamond,
+            &self.trail_square,
+        ]
+    }
+}
+
+impl EdgeMap {
+    // Find the first edge partitioning for which every path type forms a single trail
+    pub fn solve(grid: &Grid) -> Result<Solution, Error> {
+        EdgeMap::solve_from(grid, &EdgeMap::empty(grid))
+    }
+
+    // Find the first solution that includes the given edges, such as the paths
+    // already drawn on a board in play
+    pub fn solve_from(grid: &Grid, drawn: &EdgeMap) -> Result<Solution, Error> {
+        let result = EdgeMap::find(grid, |edgemap| {
+            if !drawn.is_subset_of(edgemap) {
+                return Ok(());
+            }
+
+            let trail_triangle = edgemap.trail(grid, PathType::Triangle);
+            let trail_diamond = edgemap.trail(grid, PathType::Diamond);
+            let trail_square = edgemap.trail(grid, PathType::Square);
+
+            if let Some(trail_triangle) = trail_triangle
+                && let Some(trail_diamond) = trail_diamond
+                && let Some(trail_square) = trail_square
+            {
+                Err(Solution {
+                    edgemap: edgemap.clone(),
+                    trail_triangle,
+                    trail_diamond,
+                    trail_square,
+                })
+            } else {
+                Ok(())
+            }
+        });
+
+        match result {
+            Ok(()) => Err(Error::Unsolvable),
+            Err(solution) => Ok(solution),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    UnsupportedImageFormat(String),
+    InvalidRegion(String),
+    X11(String),
+    Config(String),
+    BoardNotFound,
+    UnknownColorScheme([u8; 3]),
+    MalformedTileLayout(String),
+    Unsolvable,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::UnsupportedImageFormat(what) => write!(f, "Unsupported image format: {}", what),
+            Error::InvalidRegion(what) => write!(f, "Invalid image region: {}", what),
+            Error::X11(what) => write!(f, "X11 error: {}", what),
+            Error::Config(what) => write!(f, "Configuration error: {}", what),
+            Error::BoardNotFound => write!(f, "Can't find a LYNE board in image"),
+            Error::UnknownColorScheme(border_color) => write!(
+                f,
+                "Can't determine color scheme for image (border color {:?})",
+                border_color
+            ),
+            Error::MalformedTileLayout(what) => write!(f, "Malformed tile layout: {}", what),
+            Error::Unsolvable => write!(f, "Puzzle has no solution"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for Error {
+    fn from(err: png::DecodingError) -> Self {
+        match err {
+            png::DecodingError::IoError(err) => Error::Io(err),
+            err => Error::UnsupportedImageFormat(err.to_string()),
+        }
+    }
+}
+
+impl From<png::EncodingError> for Error {
+    fn from(err: png::EncodingError) -> Self {
+        match err {
+            png::EncodingError::IoError(err) => Error::Io(err),
+            err => Error::UnsupportedImageFormat(err.to_string()),
+        }
+    }
+}
+
+impl From<gif::DecodingError> for Error {
+    fn from(err: gif::DecodingError) -> Self {
+        match err {
+            gif::DecodingError::Io(err) => Error::Io(err),
+            err => Error::UnsupportedImageFormat(err.to_string()),
+        }
+    }
+}
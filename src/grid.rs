@@ -1,49 +1,144 @@
 use std::{fmt, iter};
 
-use crate::tile::{Tile, TileType};
+use crate::{
+    Error,
+    tile::{Tile, TileType},
+};
+
+// Boards with more rows or columns than this are taken to be a misfit lattice
+const MAX_CELLS: usize = 32;
 
 #[derive(Debug)]
 pub struct Grid {
     pub cells: Vec<Vec<TileType>>,
+
+    // Cell centers rounded to whole pixels, from the lattice
     pub column_x: Vec<u16>,
     pub row_y: Vec<u16>,
+
+    pub lattice: Lattice,
+}
+
+// The geometry of the board fitted to the tile positions: the center of the cell at
+// (row, column) is at (origin_x + column * pitch_x, origin_y + row * pitch_y)
+#[derive(Clone, Copy, Debug)]
+pub struct Lattice {
+    pub origin_x: f64,
+    pub origin_y: f64,
+    pub pitch_x: f64,
+    pub pitch_y: f64,
 }
 
 impl Grid {
-    pub fn from_tiles(tiles: &[Tile]) -> Self {
-        let mut column_x = tiles.iter().map(|tile| tile.grid_x).collect::<Vec<_>>();
-        column_x.sort();
-        column_x.dedup_by(|a, b| a.abs_diff(*b) <= 1);
+    // Fits a lattice to the tile positions by least squares, so that rows and
+    // columns are found even if tile positions jitter by a few pixels, and rows and
+    // columns without any tiles between the outermost ones are kept as empty cells.
+    pub fn from_tiles(tiles: &[Tile]) -> Result<Self, Error> {
+        if tiles.is_empty() {
+            return Err(Error::MalformedTileLayout("No tiles found".to_string()));
+        }
 
-        let mut row_y = tiles.iter().map(|tile| tile.grid_y).collect::<Vec<_>>();
-        row_y.sort();
-        row_y.dedup_by(|a, b| a.abs_diff(*b) <= 1);
+        let xs = tiles
+            .iter()
+            .map(|tile| f64::from(tile.grid_x))
+            .collect::<Vec<_>>();
+        let ys = tiles
+            .iter()
+            .map(|tile| f64::from(tile.grid_y))
+            .collect::<Vec<_>>();
 
-        let mut cells = iter::repeat_with(|| {
-            iter::repeat_n(TileType::Empty, column_x.len()).collect::<Vec<_>>()
-        })
-        .take(row_y.len())
-        .collect::<Vec<_>>();
+        let tolerance = jitter_tolerance(&xs, &ys);
 
-        for tile in tiles {
-            let column = column_x
-                .iter()
-                .position(|x| x.abs_diff(tile.grid_x) <= 1)
-                .unwrap();
+        // Cells are square, so the smallest spacing between rows or columns of tiles
+        // is the pitch in both directions (unless every other row and column is
+        // empty, which can't be told apart from a coarser lattice)
+        let pitch = smallest_gap(&clusters(&xs, tolerance))
+            .into_iter()
+            .chain(smallest_gap(&clusters(&ys, tolerance)))
+            .reduce(f64::min);
 
-            let row = row_y
-                .iter()
-                .position(|y| y.abs_diff(tile.grid_y) <= 1)
-                .unwrap();
+        let (origin_x, pitch_x, columns) = fit_axis(&xs, pitch);
+        let (origin_y, pitch_y, rows) = fit_axis(&ys, pitch);
+
+        let lattice = Lattice {
+            origin_x,
+            origin_y,
+            pitch_x,
+            pitch_y,
+        };
+
+        let num_columns = columns.iter().max().unwrap() + 1;
+        let num_rows = rows.iter().max().unwrap() + 1;
+
+        if num_columns > MAX_CELLS || num_rows > MAX_CELLS {
+            return Err(Error::MalformedTileLayout(format!(
+                "Tile positions don't fit a regular grid (would need {}x{} cells)",
+                num_columns, num_rows
+            )));
+        }
+
+        let mut cells =
+            iter::repeat_with(|| iter::repeat_n(TileType::Empty, num_columns).collect::<Vec<_>>())
+                .take(num_rows)
+                .collect::<Vec<_>>();
+
+        for ((tile, column), row) in tiles.iter().zip(columns).zip(rows) {
+            let (x, y) = lattice.center(row, column);
+
+            if (f64::from(tile.grid_x) - x).abs() > tolerance
+                || (f64::from(tile.grid_y) - y).abs() > tolerance
+            {
+                return Err(Error::MalformedTileLayout(format!(
+                    "Tile at ({}, {}) is not aligned to the grid",
+                    tile.grid_x, tile.grid_y
+                )));
+            }
+
+            if cells[row][column] != TileType::Empty {
+                return Err(Error::MalformedTileLayout(format!(
+                    "Multiple tiles at grid position ({}, {})",
+                    row, column
+                )));
+            }
 
             cells[row][column] = tile.tile_type;
         }
 
-        Self {
+        let round = |position: f64| position.round().clamp(0.0, f64::from(u16::MAX)) as u16;
+
+        Ok(Self {
             cells,
-            column_x,
-            row_y,
-        }
+            column_x: (0..num_columns)
+                .map(|column| round(lattice.center(0, column).0))
+                .collect(),
+            row_y: (0..num_rows)
+                .map(|row| round(lattice.center(row, 0).1))
+                .collect(),
+            lattice,
+        })
+    }
+
+    // The sub-pixel position of the center of a cell
+    pub fn center(&self, row: usize, column: usize) -> (f64, f64) {
+        self.lattice.center(row, column)
+    }
+
+    // Identifies the level shown on the board by the layout of its tiles, since the
+    // puzzle screen doesn't show the level's label.  Rows are separated by dashes,
+    // and empty cells are dots, so a key can be used as a file name.
+    pub fn level_key(&self) -> String {
+        self.cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| match tile {
+                        TileType::Empty => '.',
+                        tile => tile.as_char(),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("-")
     }
 
     pub fn rows(&self) -> usize {
@@ -55,6 +150,111 @@ impl Grid {
     }
 }
 
+impl Lattice {
+    pub fn center(&self, row: usize, column: usize) -> (f64, f64) {
+        (
+            self.origin_x + column as f64 * self.pitch_x,
+            self.origin_y + row as f64 * self.pitch_y,
+        )
+    }
+}
+
+// How far tile positions may be from their cell centers: a quarter of the typical
+// distance between neighboring tiles, which is a cell or a diagonal apart
+fn jitter_tolerance(xs: &[f64], ys: &[f64]) -> f64 {
+    let mut nearest = (0..xs.len())
+        .map(|i| {
+            (0..xs.len())
+                .filter(|j| *j != i)
+                .map(|j| (xs[i] - xs[j]).hypot(ys[i] - ys[j]))
+                .fold(f64::INFINITY, f64::min)
+        })
+        .collect::<Vec<_>>();
+
+    nearest.sort_by(f64::total_cmp);
+
+    let median = nearest[nearest.len() / 2];
+
+    if median.is_finite() {
+        median / 4.0
+    } else {
+        // A single tile is trivially aligned
+        f64::INFINITY
+    }
+}
+
+// Groups positions that are within the tolerance of their neighbors, returning the
+// mean position of each group in increasing order
+fn clusters(positions: &[f64], tolerance: f64) -> Vec<f64> {
+    let mut sorted = positions.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let mut means = Vec::new();
+    let mut group: Vec<f64> = Vec::new();
+
+    for position in sorted {
+        if let Some(last) = group.last()
+            && position - last > tolerance
+        {
+            means.push(group.iter().sum::<f64>() / group.len() as f64);
+            group.clear();
+        }
+
+        group.push(position);
+    }
+
+    means.push(group.iter().sum::<f64>() / group.len() as f64);
+
+    means
+}
+
+fn smallest_gap(means: &[f64]) -> Option<f64> {
+    means
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .reduce(f64::min)
+}
+
+// Assigns each position an index on a lattice with the given approximate pitch, and
+// fits the lattice's origin and pitch to the positions by least squares
+fn fit_axis(positions: &[f64], pitch: Option<f64>) -> (f64, f64, Vec<usize>) {
+    let first = positions.iter().copied().fold(f64::INFINITY, f64::min);
+
+    let indices = positions
+        .iter()
+        .map(|position| match pitch {
+            Some(pitch) => ((position - first) / pitch).round() as usize,
+            None => 0,
+        })
+        .collect::<Vec<_>>();
+
+    let n = positions.len() as f64;
+
+    let mean_index = indices.iter().sum::<usize>() as f64 / n;
+    let mean_position = positions.iter().sum::<f64>() / n;
+
+    let (covariance, variance) = indices.iter().zip(positions).fold(
+        (0.0, 0.0),
+        |(covariance, variance), (index, position)| {
+            let d = *index as f64 - mean_index;
+
+            (
+                covariance + d * (position - mean_position),
+                variance + d * d,
+            )
+        },
+    );
+
+    // Without at least two distinct indices only the origin can be fitted
+    let pitch = if variance > 0.0 {
+        covariance / variance
+    } else {
+        pitch.unwrap_or(0.0)
+    };
+
+    (mean_position - pitch * mean_index, pitch, indices)
+}
+
 impl fmt::Display for Grid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         write!(
@@ -72,3 +272,74 @@ impl fmt::Display for Grid {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(grid_x: u16, grid_y: u16, tile_type: TileType) -> Tile {
+        Tile {
+            grid_x,
+            grid_y,
+            tile_type,
+        }
+    }
+
+    #[test]
+    fn fit_jittered_tiles() {
+        // The middle row is empty, and positions are off by up to two pixels either way
+        let grid = Grid::from_tiles(&[
+            tile(101, 99, TileType::TriangleTerminal),
+            tile(149, 101, TileType::Connect2),
+            tile(202, 100, TileType::TriangleTerminal),
+            tile(99, 201, TileType::Square),
+            tile(151, 199, TileType::Square),
+            tile(198, 200, TileType::SquareTerminal),
+        ])
+        .unwrap();
+
+        assert_eq!(grid.level_key(), "T2T-...-ssS");
+        assert_eq!(grid.column_x, [100, 150, 200]);
+        assert_eq!(grid.row_y, [100, 150, 200]);
+        assert!((grid.lattice.pitch_x - 50.0).abs() < 1.0);
+        assert!((grid.lattice.pitch_y - 50.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn reject_misfit_tiles() {
+        // Two tiles in the same cell
+        assert!(
+            Grid::from_tiles(&[
+                tile(100, 100, TileType::Square),
+                tile(150, 100, TileType::Square),
+                tile(200, 100, TileType::Square),
+                tile(152, 102, TileType::Square),
+            ])
+            .is_err()
+        );
+
+        // Too far apart for the spacing of the others
+        assert!(
+            Grid::from_tiles(&[
+                tile(100, 100, TileType::Square),
+                tile(110, 100, TileType::Square),
+                tile(1000, 100, TileType::Square),
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn level_key() {
+        let grid = Grid::from_tiles(&[
+            tile(100, 100, TileType::TriangleTerminal),
+            tile(150, 100, TileType::Connect2),
+            tile(200, 100, TileType::TriangleTerminal),
+            tile(150, 150, TileType::Square),
+            tile(200, 200, TileType::SquareTerminal),
+        ])
+        .unwrap();
+
+        assert_eq!(grid.level_key(), "T2T-.s.-..S");
+    }
+}
@@ -0,0 +1,294 @@
+use std::fs;
+
+use super::{Image, check_masks, extract_channel, rgb::ImageRgb};
+use crate::Error;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+// Supports uncompressed and bitfield-encoded BMP images at 1, 4, 8, 16, 24 and 32
+// bits per pixel, both bottom-up and top-down.  Everything is converted to RGB.
+pub fn open(file: &str) -> Result<Box<dyn Image>, Error> {
+    from_bytes(&fs::read(file)?)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Box<dyn Image>, Error> {
+    if !bytes.starts_with(b"BM") {
+        return Err(Error::UnsupportedImageFormat("Not a BMP image".to_string()));
+    }
+
+    let data_offset = usize::try_from(read_u32(bytes, 10)?).unwrap();
+    let header_size = usize::try_from(read_u32(bytes, 14)?).unwrap();
+
+    let (width, height, bits_per_pixel, compression, palette_entry_size, colors_used) =
+        if header_size == 12 {
+            (
+                i64::from(read_u16(bytes, 18)?),
+                i64::from(read_u16(bytes, 20)? as i16),
+                read_u16(bytes, 24)?,
+                BI_RGB,
+                3,
+                0,
+            )
+        } else if header_size >= 40 {
+            (
+                i64::from(read_u32(bytes, 18)? as i32),
+                i64::from(read_u32(bytes, 22)? as i32),
+                read_u16(bytes, 28)?,
+                read_u32(bytes, 30)?,
+                4,
+                read_u32(bytes, 46)?,
+            )
+        } else {
+            return Err(Error::UnsupportedImageFormat(format!(
+                "Unknown BMP header size {}",
+                header_size
+            )));
+        };
+
+    // A negative height denotes a top-down image
+    let top_down = height < 0;
+
+    let (Ok(width @ 1..), Ok(height @ 1..)) = (u32::try_from(width), u32::try_from(height.abs()))
+    else {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "Invalid BMP dimensions {}x{}",
+            width, height
+        )));
+    };
+
+    let masks = match (compression, bits_per_pixel) {
+        (BI_RGB, 16) => Some([0x7c00, 0x03e0, 0x001f]),
+        (BI_RGB, 32) => Some([0x00ff0000, 0x0000ff00, 0x000000ff]),
+        (BI_RGB, 1 | 4 | 8 | 24) => None,
+        (BI_BITFIELDS, 16 | 32) => {
+            // The masks follow a BITMAPINFOHEADER, and are part of later headers
+            let masks = [
+                read_u32(bytes, 54)?,
+                read_u32(bytes, 58)?,
+                read_u32(bytes, 62)?,
+            ];
+
+            check_masks(masks, "BMP")?;
+
+            Some(masks)
+        }
+        _ => {
+            return Err(Error::UnsupportedImageFormat(format!(
+                "Unsupported BMP compression {} and/or bit depth {}",
+                compression, bits_per_pixel
+            )));
+        }
+    };
+
+    let palette = if bits_per_pixel <= 8 {
+        let num_colors = if colors_used == 0 {
+            1 << bits_per_pixel
+        } else {
+            usize::try_from(colors_used)
+                .unwrap()
+                .min(1 << bits_per_pixel)
+        };
+
+        let palette_offset = 14 + header_size;
+
+        (0..num_colors)
+            .map(|i| {
+                let entry = bytes
+                    .get(palette_offset + palette_entry_size * i..)
+                    .and_then(|entry| entry.get(..3))
+                    .ok_or_else(truncated)?;
+
+                Ok([entry[2], entry[1], entry[0]])
+            })
+            .collect::<Result<Vec<_>, Error>>()?
+    } else {
+        Vec::new()
+    };
+
+    let width_usize = usize::try_from(width).unwrap();
+    let height_usize = usize::try_from(height).unwrap();
+    let bits_per_pixel = usize::from(bits_per_pixel);
+
+    // Rows are padded to a multiple of 4 bytes.  The pixel data has to be present in
+    // full before anything is allocated for it, so that the dimensions in the header
+    // can't make us allocate more than a small multiple of the file size.
+    let stride = bits_per_pixel
+        .checked_mul(width_usize)
+        .map(|bits| bits.div_ceil(32) * 4)
+        .ok_or_else(truncated)?;
+
+    let data = stride
+        .checked_mul(height_usize)
+        .and_then(|size| bytes.get(data_offset..)?.get(..size))
+        .ok_or_else(truncated)?;
+
+    let mut rgb = Vec::with_capacity(3 * width_usize * height_usize);
+
+    for y in 0..height_usize {
+        let source_row = if top_down { y } else { height_usize - 1 - y };
+
+        let row = &data[stride * source_row..stride * (source_row + 1)];
+
+        for x in 0..width_usize {
+            let pixel = match (bits_per_pixel, masks) {
+                (1 | 4 | 8, _) => {
+                    let bit_offset = bits_per_pixel * x;
+                    let byte = row[bit_offset / 8];
+                    let index = (byte >> (8 - bits_per_pixel - bit_offset % 8))
+                        & (0xff >> (8 - bits_per_pixel));
+
+                    *palette.get(usize::from(index)).ok_or_else(|| {
+                        Error::UnsupportedImageFormat(format!(
+                            "BMP palette index {} out of range",
+                            index
+                        ))
+                    })?
+                }
+                (24, _) => [row[3 * x + 2], row[3 * x + 1], row[3 * x]],
+                (16, Some(masks)) => {
+                    let value = u32::from(u16::from_le_bytes([row[2 * x], row[2 * x + 1]]));
+
+                    masks.map(|mask| extract_channel(value, mask))
+                }
+                (32, Some(masks)) => {
+                    let value = u32::from_le_bytes([
+                        row[4 * x],
+                        row[4 * x + 1],
+                        row[4 * x + 2],
+                        row[4 * x + 3],
+                    ]);
+
+                    masks.map(|mask| extract_channel(value, mask))
+                }
+                _ => unreachable!(),
+            };
+
+            rgb.extend(pixel);
+        }
+    }
+
+    Ok(Box::new(ImageRgb {
+        bytes: rgb,
+        width,
+        height,
+        line_size: 3 * width_usize,
+    }))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, Error> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(truncated)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Error> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(truncated)
+}
+
+fn truncated() -> Error {
+    Error::UnsupportedImageFormat("Truncated BMP image".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 24-bit image with a BITMAPINFOHEADER and the given pixel data
+    fn bmp_24(width: i32, height: i32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"BM".to_vec();
+
+        bytes.extend(u32::try_from(54 + data.len()).unwrap().to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(54u32.to_le_bytes());
+        bytes.extend(40u32.to_le_bytes());
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(24u16.to_le_bytes());
+        bytes.extend([0; 24]);
+        bytes.extend(data);
+
+        bytes
+    }
+
+    // A 1x1 32-bit image with a BITMAPINFOHEADER followed by the given color masks
+    fn bmp_bitfields(masks: [u32; 3], pixel: u32) -> Vec<u8> {
+        let mut bytes = bmp_24(1, 1, &[]);
+
+        bytes[2..6].copy_from_slice(&70u32.to_le_bytes());
+        bytes[10..14].copy_from_slice(&66u32.to_le_bytes());
+        bytes[28..30].copy_from_slice(&32u16.to_le_bytes());
+        bytes[30..34].copy_from_slice(&BI_BITFIELDS.to_le_bytes());
+        bytes.extend(masks.iter().flat_map(|mask| mask.to_le_bytes()));
+        bytes.extend(pixel.to_le_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn decode() {
+        let data = [
+            0, 0, 255, 0, 255, 0, 0, 0, // bottom row: red, green, padding
+            255, 0, 0, 255, 255, 255, 0, 0, // top row: blue, white, padding
+        ];
+
+        let image = from_bytes(&bmp_24(2, 2, &data)).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.pixel(0, 0), (0, 0, 255));
+        assert_eq!(image.pixel(1, 0), (255, 255, 255));
+        assert_eq!(image.pixel(0, 1), (255, 0, 0));
+        assert_eq!(image.pixel(1, 1), (0, 255, 0));
+    }
+
+    #[test]
+    fn huge_dimensions() {
+        assert!(from_bytes(&bmp_24(i32::MAX, i32::MAX, &[0; 16])).is_err());
+        assert!(from_bytes(&bmp_24(i32::MAX, i32::MIN + 1, &[0; 16])).is_err());
+        assert!(from_bytes(&bmp_24(1, 1 << 30, &[0; 16])).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(from_bytes(&bmp_24(0, 2, &[0; 16])).is_err());
+        assert!(from_bytes(&bmp_24(2, 2, &[0; 15])).is_err());
+
+        let mut bytes = bmp_24(2, 2, &[0; 16]);
+        bytes[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(from_bytes(&bytes).is_err());
+
+        for length in 0..54 {
+            assert!(from_bytes(&bmp_24(2, 2, &[0; 16])[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn bitfields() {
+        let image = from_bytes(&bmp_bitfields([0xff, 0xff00, 0x00ff0000], 0x00030201)).unwrap();
+
+        assert_eq!(image.pixel(0, 0), (1, 2, 3));
+
+        let image = from_bytes(&bmp_bitfields([0xf800, 0x07e0, 0x001f], 0xffe0)).unwrap();
+
+        assert_eq!(image.pixel(0, 0), (255, 255, 0));
+    }
+
+    #[test]
+    fn malformed_masks() {
+        for masks in [
+            [0b101, 0xff00, 0xff0000],
+            [0xff, 0, 0xff0000],
+            [0xff, 0xff00, 0x80000001],
+        ] {
+            assert!(matches!(
+                from_bytes(&bmp_bitfields(masks, u32::MAX)),
+                Err(Error::UnsupportedImageFormat(_))
+            ));
+        }
+    }
+}
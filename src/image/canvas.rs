@@ -0,0 +1,212 @@
+use std::rc::Rc;
+
+use super::Image;
+use crate::Error;
+
+// Largest canvas that will be allocated, well above any screen resolution
+const MAX_CANVAS_PIXELS: u64 = 1 << 26;
+
+// RGBA canvas onto which the frames of an animated image are composited.  The
+// bytes are shared with the emitted frames and only copied when the canvas is
+// drawn onto while a previous frame is still in use.
+pub struct Canvas {
+    bytes: Rc<Vec<u8>>,
+    width: u32,
+    height: u32,
+}
+
+// How to dispose of a frame before drawing the next one
+pub enum Dispose {
+    Clear(u32, u32, u32, u32),
+    // The saved contents of the region covered by the frame
+    Restore(u32, u32, u32, u32, Vec<u8>),
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Result<Self, Error> {
+        if u64::from(width) * u64::from(height) > MAX_CANVAS_PIXELS {
+            return Err(Error::UnsupportedImageFormat(format!(
+                "Animation canvas of {width}x{height} pixels is too large"
+            )));
+        }
+
+        Ok(Self {
+            bytes: Rc::new(vec![
+                0;
+                4 * usize::try_from(width).unwrap()
+                    * usize::try_from(height).unwrap()
+            ]),
+            width,
+            height,
+        })
+    }
+
+    // Draws an RGBA subimage onto the canvas.  If blend is set, (partially)
+    // transparent pixels are alpha blended onto the existing canvas contents,
+    // otherwise they replace them.
+    pub fn draw(&mut self, rgba: &[u8], left: u32, top: u32, width: u32, height: u32, blend: bool) {
+        let (clipped_width, clipped_height) = self.clip(left, top, width, height);
+        let canvas_width = self.width;
+        let bytes = Rc::make_mut(&mut self.bytes);
+
+        for (x, y) in (0..clipped_height).flat_map(|y| (0..clipped_width).map(move |x| (x, y))) {
+            let src = 4 * usize::try_from(y * width + x).unwrap();
+            let dst = offset(canvas_width, left + x, top + y);
+
+            let Some(src) = rgba.get(src..src + 4) else {
+                return;
+            };
+
+            let alpha = u32::from(src[3]);
+
+            if !blend || alpha == 255 {
+                bytes[dst..dst + 4].copy_from_slice(src);
+            } else if alpha != 0 {
+                for (old, new) in bytes[dst..dst + 3].iter_mut().zip(&src[..3]) {
+                    *old = u8::try_from(
+                        (u32::from(*new) * alpha + u32::from(*old) * (255 - alpha) + 127) / 255,
+                    )
+                    .unwrap();
+                }
+
+                bytes[dst + 3] = bytes[dst + 3].max(src[3]);
+            }
+        }
+    }
+
+    // Saves the contents of a region, to restore it after the frame covering it
+    pub fn save(&self, left: u32, top: u32, width: u32, height: u32) -> Dispose {
+        let (width, height) = self.clip(left, top, width, height);
+        let mut saved = Vec::with_capacity(4 * usize::try_from(width * height).unwrap());
+
+        for y in top..top + height {
+            let start = self.offset(left, y);
+
+            saved
+                .extend_from_slice(&self.bytes[start..start + 4 * usize::try_from(width).unwrap()]);
+        }
+
+        Dispose::Restore(left, top, width, height, saved)
+    }
+
+    pub fn dispose(&mut self, dispose: Dispose) {
+        match dispose {
+            Dispose::Clear(left, top, width, height) => {
+                let (width, height) = self.clip(left, top, width, height);
+                let row_size = 4 * usize::try_from(width).unwrap();
+
+                for y in top..top + height {
+                    let start = self.offset(left, y);
+
+                    Rc::make_mut(&mut self.bytes)[start..start + row_size].fill(0);
+                }
+            }
+            Dispose::Restore(left, top, width, height, saved) => {
+                let row_size = 4 * usize::try_from(width).unwrap();
+
+                for (y, row) in (top..top + height).zip(saved.chunks_exact(row_size.max(1))) {
+                    let start = self.offset(left, y);
+
+                    Rc::make_mut(&mut self.bytes)[start..start + row_size].copy_from_slice(row);
+                }
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Box<dyn Image> {
+        Box::new(Frame {
+            bytes: Rc::clone(&self.bytes),
+            width: self.width,
+            height: self.height,
+        })
+    }
+
+    // Returns the size of a region after clipping it to the canvas
+    fn clip(&self, left: u32, top: u32, width: u32, height: u32) -> (u32, u32) {
+        let width = width.min(self.width.saturating_sub(left));
+        let height = height.min(self.height.saturating_sub(top));
+
+        if width == 0 || height == 0 {
+            (0, 0)
+        } else {
+            (width, height)
+        }
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        offset(self.width, x, y)
+    }
+}
+
+fn offset(width: u32, x: u32, y: u32) -> usize {
+    4 * (usize::try_from(y).unwrap() * usize::try_from(width).unwrap()
+        + usize::try_from(x).unwrap())
+}
+
+// A composited frame.  The alpha channel is ignored, as screenshots are expected
+// to be opaque.
+struct Frame {
+    bytes: Rc<Vec<u8>>,
+    width: u32,
+    height: u32,
+}
+
+impl Image for Frame {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+        let pixel_index = offset(self.width, x, y);
+
+        (
+            self.bytes[pixel_index],
+            self.bytes[pixel_index + 1],
+            self.bytes[pixel_index + 2],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispose() {
+        let mut canvas = Canvas::new(3, 2).unwrap();
+
+        canvas.draw(&[10, 20, 30, 255].repeat(6), 0, 0, 3, 2, false);
+
+        let before = canvas.snapshot();
+        let restore = canvas.save(2, 1, 4, 4);
+
+        canvas.draw(&[1, 2, 3, 255].repeat(16), 2, 1, 4, 4, false);
+        canvas.dispose(Dispose::Clear(0, 0, 1, 5));
+
+        let after = canvas.snapshot();
+
+        // Earlier frames are unaffected by drawing onto the canvas
+        assert_eq!(before.pixel(2, 1), (10, 20, 30));
+        assert_eq!(before.pixel(0, 0), (10, 20, 30));
+        assert_eq!(after.pixel(2, 1), (1, 2, 3));
+        assert_eq!(after.pixel(0, 1), (0, 0, 0));
+
+        canvas.dispose(restore);
+
+        let restored = canvas.snapshot();
+
+        assert_eq!(restored.pixel(2, 1), (10, 20, 30));
+        assert_eq!(restored.pixel(0, 1), (0, 0, 0));
+        assert_eq!(after.pixel(2, 1), (1, 2, 3));
+    }
+
+    #[test]
+    fn oversized() {
+        assert!(Canvas::new(65535, 65535).is_err());
+        assert!(Canvas::new(7680, 4320).is_ok());
+    }
+}
@@ -0,0 +1,253 @@
+use std::{collections::HashSet, ops::Range};
+
+use super::{Image, rgb::ImageRgb};
+
+// GIF recordings reduce each frame to a palette of at most this many colors
+const MAX_PALETTE_SIZE: usize = 256;
+
+// Width and height of the window over which undither averages.  Ordered dithering
+// repeats every 2 or 4 pixels, so a 4x4 window covers whole periods of it.
+const UNDITHER_SIZE: usize = 4;
+
+// Averaging restores the colors of an image to within this (non-squared) distance,
+// so detection needs at least this color tolerance on undithered images
+pub const UNDITHERED_COLOR_TOLERANCE: u32 = 24;
+
+// Whether the image looks like it was reduced to a palette by dithering: it has few
+// colors, and yet most pixels differ from their neighbor.  Screenshots of LYNE
+// consist of areas of flat color, and noisy images have many more colors.
+pub fn is_dithered<T: Image + ?Sized>(image: &T) -> bool {
+    let mut colors = HashSet::new();
+    let (mut pairs, mut changes) = (0u64, 0u64);
+
+    for y in 0..image.height() {
+        let mut previous = None;
+
+        for x in 0..image.width() {
+            let p = image.pixel(x, y);
+
+            colors.insert(p);
+
+            if colors.len() > MAX_PALETTE_SIZE {
+                return false;
+            }
+
+            if let Some(previous) = previous {
+                pairs += 1;
+
+                if previous != p {
+                    changes += 1;
+                }
+            }
+
+            previous = Some(p);
+        }
+    }
+
+    changes * 2 > pairs
+}
+
+// Averages out dithering by replacing each pixel with the mean of the pixels around
+// it.  Edges between areas of flat color are blurred by a pixel or two, which tile
+// detection already allows for in scaled screenshots.
+pub fn undither<T: Image + ?Sized>(image: &T) -> Box<dyn Image> {
+    let (width, height) = (image.width(), image.height());
+    let (w, h) = (
+        usize::try_from(width).unwrap(),
+        usize::try_from(height).unwrap(),
+    );
+
+    let pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| image.pixel(x, y)))
+        .map(|p| [p.0, p.1, p.2].map(u32::from))
+        .collect::<Vec<_>>();
+
+    // The box filter is separable: sum along rows first, and then along columns
+    let mut row_sums = vec![[0; 3]; w * h];
+
+    for y in 0..h {
+        for x in 0..w {
+            for i in window(x, w) {
+                add(&mut row_sums[y * w + x], &pixels[y * w + i]);
+            }
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(3 * w * h);
+
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0; 3];
+
+            for j in window(y, h) {
+                add(&mut sum, &row_sums[j * w + x]);
+            }
+
+            let count = u32::try_from(window(x, w).len() * window(y, h).len()).unwrap();
+
+            bytes.extend(sum.map(|sum| u8::try_from((sum + count / 2) / count).unwrap()));
+        }
+    }
+
+    Box::new(ImageRgb {
+        bytes,
+        width,
+        height,
+        line_size: 3 * w,
+    })
+}
+
+// The pixels averaged for the pixel at the given position, clipped to the image
+fn window(center: usize, len: usize) -> Range<usize> {
+    center.saturating_sub(UNDITHER_SIZE / 2)..(center + UNDITHER_SIZE.div_ceil(2)).min(len)
+}
+
+fn add(sum: &mut [u32; 3], value: &[u32; 3]) {
+    for (sum, value) in sum.iter_mut().zip(value) {
+        *sum += value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::Board,
+        detector::ColorTableDetector,
+        edgemap::{EdgeMap, PathType},
+        image,
+        screen::Screen,
+        tile::{DetectOptions, Tile},
+    };
+
+    // A dithered GIF recording of a level being played
+    fn recording_frame(index: usize) -> Box<dyn Image> {
+        image::open_frames(concat!(env!("CARGO_MANIFEST_DIR"), "/z23.gif"))
+            .unwrap()
+            .nth(index)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn detect_dithering() {
+        let frame = recording_frame(0);
+
+        assert!(is_dithered(&*frame));
+        assert!(!is_dithered(&*undither(&*frame)));
+    }
+
+    #[test]
+    fn read_recording() {
+        let options = DetectOptions {
+            color_tolerance: UNDITHERED_COLOR_TOLERANCE,
+            ..Default::default()
+        };
+
+        let image = undither(&*recording_frame(0));
+        let board = Board::locate(&*image, &options).unwrap();
+        let color_scheme = board.detect_color_scheme(&*image, &options).unwrap();
+        let detector = ColorTableDetector {
+            color_scheme: Some(&color_scheme),
+            options,
+        };
+        let interpretation = board
+            .detect(&*image, &detector, None)
+            .unwrap()
+            .solve()
+            .unwrap();
+
+        assert_eq!(
+            interpretation.grid.level_key(),
+            "t2tdds2S-223tdd2S-T3D4D3ss-ttdTdsss"
+        );
+    }
+
+    #[test]
+    fn classify_recording() {
+        let options = DetectOptions {
+            color_tolerance: UNDITHERED_COLOR_TOLERANCE,
+            ..Default::default()
+        };
+
+        let classify = |index| Screen::classify(&*undither(&*recording_frame(index)), &options);
+
+        assert_eq!(
+            classify(60),
+            Screen::InProgress("t2tdds2S-223tdd2S-T3D4D3ss-ttdTdsss".to_string())
+        );
+        assert_eq!(classify(262), Screen::Solved);
+
+        // The tiles change color on the way to the next level
+        assert_eq!(classify(263), Screen::Transition);
+        assert_eq!(
+            classify(275),
+            Screen::InProgress("STsTdddS-t4d33sd2-2tD3t33d-tttt2dDd".to_string())
+        );
+
+        let blank = ImageRgb {
+            bytes: vec![0; 3 * 640 * 480],
+            width: 640,
+            height: 480,
+            line_size: 3 * 640,
+        };
+
+        assert_eq!(Screen::classify(&blank, &options), Screen::NotPuzzle);
+    }
+
+    #[test]
+    fn read_drawn_paths() {
+        let options = DetectOptions {
+            color_tolerance: UNDITHERED_COLOR_TOLERANCE,
+            ..Default::default()
+        };
+
+        let image = undither(&*recording_frame(0));
+        let board = Board::locate(&*image, &options).unwrap();
+        let color_scheme = board.detect_color_scheme(&*image, &options).unwrap();
+        let detector = ColorTableDetector {
+            color_scheme: Some(&color_scheme),
+            options,
+        };
+        let grid = board
+            .detect(&*image, &detector, None)
+            .unwrap()
+            .solve()
+            .unwrap()
+            .grid;
+
+        assert!(EdgeMap::detect_drawn(&*image, &grid, &color_scheme, &options).is_empty());
+
+        // The last frame before the level is solved has every path drawn, including
+        // diagonal paths with both outlines on either side of the line between the
+        // tiles' centers
+        let image = undither(&*recording_frame(262));
+        let drawn = EdgeMap::detect_drawn(&*image, &grid, &color_scheme, &options);
+        let solution = EdgeMap::solve_from(&grid, &drawn).unwrap();
+
+        assert!(solution.edgemap.is_subset_of(&drawn));
+        assert!(drawn.is_subset_of(&solution.edgemap));
+        assert_eq!(drawn.cells[0][1].dr, Some(PathType::Triangle));
+        assert_eq!(drawn.cells[1][4].dl, Some(PathType::Diamond));
+        assert_eq!(drawn.cells[2][3].dl, Some(PathType::Diamond));
+    }
+
+    #[test]
+    fn detect_tiles() {
+        let options = DetectOptions {
+            color_tolerance: UNDITHERED_COLOR_TOLERANCE,
+            ..Default::default()
+        };
+
+        let image = undither(&*recording_frame(0));
+        let tiles = Tile::detect_tiles_with(&*image, &options).unwrap();
+
+        assert_eq!(tiles.len(), 32);
+
+        // Within a region around the board, with coordinates in the whole image
+        assert_eq!(
+            Tile::detect_tiles_in(&*image, 500, 80, 1300, 700, &options).unwrap(),
+            tiles
+        );
+    }
+}
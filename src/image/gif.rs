@@ -0,0 +1,144 @@
+use std::{fs, io::Cursor};
+
+use gif::{ColorOutput, DecodeOptions, Decoder, DisposalMethod};
+
+use super::{
+    Frames, Image,
+    canvas::{Canvas, Dispose},
+};
+use crate::Error;
+
+// Returns the first frame of the image
+pub fn open(file: &str) -> Result<Box<dyn Image>, Error> {
+    from_bytes(&fs::read(file)?)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Box<dyn Image>, Error> {
+    frames_from_bytes(bytes.to_vec())?
+        .next()
+        .unwrap_or_else(|| {
+            Err(Error::UnsupportedImageFormat(
+                "GIF image without frames".to_string(),
+            ))
+        })
+}
+
+// Yields each frame of an animated GIF composited onto the canvas, i.e. as it would
+// be displayed.
+pub fn frames_from_bytes(bytes: Vec<u8>) -> Result<Frames, Error> {
+    let mut options = DecodeOptions::new();
+
+    options.set_color_output(ColorOutput::RGBA);
+
+    let decoder = options.read_info(Cursor::new(bytes))?;
+
+    let canvas = Canvas::new(u32::from(decoder.width()), u32::from(decoder.height()))?;
+
+    Ok(Box::new(GifFrames {
+        decoder,
+        canvas,
+        dispose: None,
+        done: false,
+    }))
+}
+
+struct GifFrames {
+    decoder: Decoder<Cursor<Vec<u8>>>,
+    canvas: Canvas,
+    // How to dispose of the previously emitted frame before drawing the next one
+    dispose: Option<Dispose>,
+    done: bool,
+}
+
+impl Iterator for GifFrames {
+    type Item = Result<Box<dyn Image>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if let Some(dispose) = self.dispose.take() {
+            self.canvas.dispose(dispose);
+        }
+
+        let frame = match self.decoder.read_next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(err) => {
+                self.done = true;
+                return Some(Err(err.into()));
+            }
+        };
+
+        let (left, top, width, height) = (
+            u32::from(frame.left),
+            u32::from(frame.top),
+            u32::from(frame.width),
+            u32::from(frame.height),
+        );
+
+        self.dispose = match frame.dispose {
+            DisposalMethod::Any | DisposalMethod::Keep => None,
+            DisposalMethod::Background => Some(Dispose::Clear(left, top, width, height)),
+            DisposalMethod::Previous => Some(self.canvas.save(left, top, width, height)),
+        };
+
+        self.canvas
+            .draw(&frame.buffer, left, top, width, height, true);
+
+        Some(Ok(self.canvas.snapshot()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Vec<u8> {
+        fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/z23.gif")).unwrap()
+    }
+
+    #[test]
+    fn decode() {
+        let image = from_bytes(&recording()).unwrap();
+
+        assert_eq!((image.width(), image.height()), (1804, 790));
+    }
+
+    #[test]
+    fn malformed() {
+        let bytes = recording();
+
+        // Just the header and logical screen descriptor, without any frames
+        assert!(from_bytes(&bytes[..13]).is_err());
+        assert!(from_bytes(&bytes[..6]).is_err());
+
+        // Cut off in the middle of the first frame
+        let mut frames = frames_from_bytes(bytes[..bytes.len() / 1000].to_vec()).unwrap();
+
+        assert!(frames.next().unwrap().is_err());
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn oversized_canvas() {
+        // A single-pixel frame on a 65535x65535 logical screen
+        let bytes = [
+            b"GIF89a".as_slice(),
+            &[0xff, 0xff, 0xff, 0xff, 0, 0, 0],
+            &[0x2c, 0, 0, 0, 0, 1, 0, 1, 0, 0],
+            &[2, 2, 0x4c, 0x01, 0, 0x3b],
+        ]
+        .concat();
+
+        assert_eq!(bytes.len(), 29);
+        assert!(matches!(
+            from_bytes(&bytes),
+            Err(Error::UnsupportedImageFormat(_))
+        ));
+    }
+}
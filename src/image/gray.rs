@@ -0,0 +1,30 @@
+use super::Image;
+
+// bytes_per_pixel is 1 for plain grayscale and 2 for grayscale with alpha.  As with
+// ImageRgba, the alpha channel is ignored.
+pub struct ImageGray {
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub line_size: usize,
+    pub bytes_per_pixel: usize,
+}
+
+impl Image for ImageGray {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+        let pixel_index = self.line_size * usize::try_from(y).unwrap()
+            + self.bytes_per_pixel * usize::try_from(x).unwrap();
+
+        let value = self.bytes[pixel_index];
+
+        (value, value, value)
+    }
+}
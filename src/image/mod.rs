@@ -1,11 +1,98 @@
 mod bgra;
+pub mod bmp;
+mod canvas;
+mod dither;
+pub mod gif;
+mod gray;
 mod indexed;
 pub mod png;
+pub mod pnm;
 mod rgb;
+mod rgba;
+mod view;
 pub mod x;
+pub mod xwd;
+
+use std::{fs, io::Read};
+
+pub use dither::{UNDITHERED_COLOR_TOLERANCE, is_dithered, undither};
+pub use view::View;
+
+use crate::Error;
 
 pub trait Image {
     fn width(&self) -> u32;
     fn height(&self) -> u32;
     fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8);
 }
+
+pub type Frames = Box<dyn Iterator<Item = Result<Box<dyn Image>, Error>>>;
+
+// Opens an image file of any supported format, based on the file's magic bytes
+pub fn open(file: &str) -> Result<Box<dyn Image>, Error> {
+    from_bytes(&fs::read(file)?)
+}
+
+pub fn from_reader(mut reader: impl Read) -> Result<Box<dyn Image>, Error> {
+    let mut bytes = Vec::new();
+
+    reader.read_to_end(&mut bytes)?;
+
+    from_bytes(&bytes)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Box<dyn Image>, Error> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => png::from_bytes(bytes),
+        [b'G', b'I', b'F', b'8', ..] => gif::from_bytes(bytes),
+        [b'B', b'M', ..] => bmp::from_bytes(bytes),
+        [b'P', b'2' | b'3' | b'5' | b'6', ..] => pnm::from_bytes(bytes),
+        _ if xwd::is_xwd(bytes) => xwd::from_bytes(bytes),
+        _ => Err(Error::UnsupportedImageFormat(
+            "Unrecognized image file signature".to_string(),
+        )),
+    }
+}
+
+// Opens an image file of any supported format and yields its frames.  Still images
+// yield a single frame.
+pub fn open_frames(file: &str) -> Result<Frames, Error> {
+    frames_from_bytes(fs::read(file)?)
+}
+
+pub fn frames_from_bytes(bytes: Vec<u8>) -> Result<Frames, Error> {
+    match bytes.as_slice() {
+        [0x89, b'P', b'N', b'G', ..] => png::frames_from_bytes(bytes),
+        [b'G', b'I', b'F', b'8', ..] => gif::frames_from_bytes(bytes),
+        _ => Ok(Box::new(std::iter::once(from_bytes(&bytes)))),
+    }
+}
+
+// Checks that each channel mask selects a single, non-empty run of bits
+fn check_masks(masks: [u32; 3], format: &str) -> Result<(), Error> {
+    for mask in masks {
+        let bits = mask >> mask.trailing_zeros().min(31);
+
+        if mask == 0 || bits.count_ones() + bits.leading_zeros() != 32 {
+            return Err(Error::UnsupportedImageFormat(format!(
+                "Invalid {} color mask {:#x}",
+                format, mask
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// Scales the bits selected by mask to the full 8-bit range
+fn extract_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let bits = mask.count_ones();
+    let channel = (value & mask) >> mask.trailing_zeros();
+    let max = (1u64 << bits) - 1;
+
+    u8::try_from((u64::from(channel) * 255 + max / 2) / max).unwrap()
+}
@@ -1,53 +1,335 @@
-use std::{fs::File, io::BufReader};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, Write},
+};
 
-use png::{BitDepth, ColorType, Decoder};
+use png::{BitDepth, BlendOp, ColorType, Decoder, DisposeOp, Encoder, Reader, Transformations};
 
-use super::{Image, indexed::ImageIndexed, rgb::ImageRgb};
+use super::{
+    Frames, Image,
+    canvas::{Canvas, Dispose},
+    gray::ImageGray,
+    indexed::ImageIndexed,
+    rgb::ImageRgb,
+    rgba::ImageRgba,
+};
+use crate::Error;
 
-pub fn open(file: &str) -> Result<Box<dyn Image>, String> {
-    let mut reader = Decoder::new(BufReader::new(File::open(file).unwrap()))
-        .read_info()
-        .unwrap();
+// 8-bit palette images are kept in indexed form.  Everything else is normalized to
+// 8 bits per channel, with 16-bit channels truncated to their most significant byte
+// and low bit depth and palette images expanded.  Alpha channels (including tRNS
+// palette transparency) are decoded but ignored.
+pub fn open(file: &str) -> Result<Box<dyn Image>, Error> {
+    decode(BufReader::new(File::open(file)?))
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Box<dyn Image>, Error> {
+    decode(Cursor::new(bytes))
+}
+
+// The decoder needs to be able to seek, so non-seekable sources such as pipes and
+// sockets are read into memory in their entirety first.
+pub fn from_reader(mut reader: impl Read) -> Result<Box<dyn Image>, Error> {
+    let mut bytes = Vec::new();
+
+    reader.read_to_end(&mut bytes)?;
+
+    from_bytes(&bytes)
+}
+
+// Writes any image out as an 8-bit RGB PNG
+pub fn save(image: &dyn Image, file: &str) -> Result<(), Error> {
+    write(image, BufWriter::new(File::create(file)?))
+}
+
+fn write(image: &dyn Image, writer: impl Write) -> Result<(), Error> {
+    let mut encoder = Encoder::new(writer, image.width(), image.height());
+
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+
+    let mut bytes = Vec::with_capacity(
+        3 * usize::try_from(image.width()).unwrap() * usize::try_from(image.height()).unwrap(),
+    );
+
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            let p = image.pixel(x, y);
+
+            bytes.extend([p.0, p.1, p.2]);
+        }
+    }
+
+    writer.write_image_data(&bytes)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+fn decode<R: BufRead + Seek>(reader: R) -> Result<Box<dyn Image>, Error> {
+    let mut decoder = Decoder::new(reader);
+
+    let info = decoder.read_header_info()?;
+
+    if info.color_type != ColorType::Indexed || info.bit_depth != BitDepth::Eight {
+        decoder.set_transformations(Transformations::normalize_to_color8());
+    }
 
-    let mut buf = vec![0; reader.output_buffer_size().unwrap()];
+    let mut reader = decoder.read_info()?;
 
-    let output_info = reader.next_frame(&mut buf).unwrap();
+    let Some(buffer_size) = reader.output_buffer_size() else {
+        return Err(Error::UnsupportedImageFormat(
+            "Image too large to decode".to_string(),
+        ));
+    };
+
+    let mut buf = vec![0; buffer_size];
+
+    let output_info = reader.next_frame(&mut buf)?;
 
     buf.truncate(output_info.buffer_size());
 
-    if output_info.bit_depth == BitDepth::Eight {
-        if output_info.color_type == ColorType::Rgb {
-            assert_eq!(
-                output_info.line_size,
-                3 * usize::try_from(output_info.width).unwrap()
-            );
+    if output_info.bit_depth != BitDepth::Eight {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "Unknown color type {:?} and/or bit depth {:?}",
+            output_info.color_type, output_info.bit_depth
+        )));
+    }
 
-            Ok(Box::new(ImageRgb {
-                bytes: buf,
-                width: output_info.width,
-                height: output_info.height,
-                line_size: output_info.line_size,
-            }))
-        } else if output_info.color_type == ColorType::Indexed {
-            assert_eq!(
-                output_info.line_size,
-                usize::try_from(output_info.width).unwrap()
-            );
+    match output_info.color_type {
+        ColorType::Grayscale | ColorType::GrayscaleAlpha => Ok(Box::new(ImageGray {
+            bytes: buf,
+            width: output_info.width,
+            height: output_info.height,
+            line_size: output_info.line_size,
+            bytes_per_pixel: output_info.color_type.samples(),
+        })),
+        ColorType::Rgb => Ok(Box::new(ImageRgb {
+            bytes: buf,
+            width: output_info.width,
+            height: output_info.height,
+            line_size: output_info.line_size,
+        })),
+        ColorType::Rgba => Ok(Box::new(ImageRgba {
+            bytes: buf,
+            width: output_info.width,
+            height: output_info.height,
+            line_size: output_info.line_size,
+        })),
+        ColorType::Indexed => {
+            let Some(palette) = reader.info().palette.as_ref() else {
+                return Err(Error::UnsupportedImageFormat(
+                    "Indexed image without palette".to_string(),
+                ));
+            };
 
             Ok(Box::new(ImageIndexed {
                 bytes: buf,
-                palette: reader.info().palette.as_ref().unwrap().to_vec(),
+                palette: palette.to_vec(),
                 width: output_info.width,
                 height: output_info.height,
                 line_size: output_info.line_size,
             }))
-        } else {
-            Err(format!("Unknown color type {:?}", output_info.color_type))
         }
-    } else {
-        Err(format!(
-            "Unknown color type {:?} and/or bit depth {:?}",
-            output_info.color_type, output_info.bit_depth
-        ))
+    }
+}
+
+// Yields each frame of an animated PNG composited onto the canvas, i.e. as it would
+// be displayed.  A non-animated PNG yields a single frame.
+pub fn frames_from_bytes(bytes: Vec<u8>) -> Result<Frames, Error> {
+    let mut decoder = Decoder::new(Cursor::new(bytes));
+
+    decoder.set_transformations(Transformations::normalize_to_color8());
+
+    let reader = decoder.read_info()?;
+
+    let Some(buffer_size) = reader.output_buffer_size() else {
+        return Err(Error::UnsupportedImageFormat(
+            "Image too large to decode".to_string(),
+        ));
+    };
+
+    let (width, height) = reader.info().size();
+
+    // The default image is not part of the animation if it isn't preceded by a fcTL
+    let (remaining_frames, skip_default_image) = match reader.info().animation_control() {
+        Some(animation_control) => {
+            let skip = reader.info().frame_control().is_none();
+
+            (animation_control.num_frames + u32::from(skip), skip)
+        }
+        None => (1, false),
+    };
+
+    Ok(Box::new(PngFrames {
+        reader,
+        buf: vec![0; buffer_size],
+        canvas: Canvas::new(width, height)?,
+        dispose: None,
+        remaining_frames,
+        skip_default_image,
+    }))
+}
+
+struct PngFrames {
+    reader: Reader<Cursor<Vec<u8>>>,
+    buf: Vec<u8>,
+    canvas: Canvas,
+    dispose: Option<Dispose>,
+    remaining_frames: u32,
+    skip_default_image: bool,
+}
+
+impl PngFrames {
+    fn next_frame(&mut self) -> Result<Box<dyn Image>, Error> {
+        if let Some(dispose) = self.dispose.take() {
+            self.canvas.dispose(dispose);
+        }
+
+        let output_info = self.reader.next_frame(&mut self.buf)?;
+
+        let rgba = to_rgba(
+            &self.buf[..output_info.buffer_size()],
+            output_info.color_type,
+            output_info.line_size,
+            output_info.width,
+        )?;
+
+        let (left, top, blend_op, dispose_op) = match self.reader.info().frame_control() {
+            Some(frame_control) => (
+                frame_control.x_offset,
+                frame_control.y_offset,
+                frame_control.blend_op,
+                frame_control.dispose_op,
+            ),
+            None => (0, 0, BlendOp::Source, DisposeOp::None),
+        };
+
+        let (width, height) = (output_info.width, output_info.height);
+
+        self.dispose = match dispose_op {
+            DisposeOp::None => None,
+            DisposeOp::Background => Some(Dispose::Clear(left, top, width, height)),
+            DisposeOp::Previous => Some(self.canvas.save(left, top, width, height)),
+        };
+
+        self.canvas
+            .draw(&rgba, left, top, width, height, blend_op == BlendOp::Over);
+
+        Ok(self.canvas.snapshot())
+    }
+}
+
+impl Iterator for PngFrames {
+    type Item = Result<Box<dyn Image>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.skip_default_image {
+            self.skip_default_image = false;
+            self.remaining_frames -= 1;
+
+            if let Err(err) = self.reader.next_frame(&mut self.buf) {
+                self.remaining_frames = 0;
+                return Some(Err(err.into()));
+            }
+        }
+
+        if self.remaining_frames == 0 {
+            return None;
+        }
+
+        self.remaining_frames -= 1;
+
+        let frame = self.next_frame();
+
+        if frame.is_err() {
+            self.remaining_frames = 0;
+        }
+
+        Some(frame)
+    }
+}
+
+fn to_rgba(
+    buf: &[u8],
+    color_type: ColorType,
+    line_size: usize,
+    width: u32,
+) -> Result<Vec<u8>, Error> {
+    let samples = color_type.samples();
+    let width = usize::try_from(width).unwrap();
+
+    let mut rgba = Vec::with_capacity(4 * width * (buf.len() / line_size.max(1)));
+
+    for row in buf.chunks_exact(line_size) {
+        for pixel in row[..samples * width].chunks_exact(samples) {
+            match color_type {
+                ColorType::Grayscale => rgba.extend([pixel[0], pixel[0], pixel[0], 255]),
+                ColorType::GrayscaleAlpha => rgba.extend([pixel[0], pixel[0], pixel[0], pixel[1]]),
+                ColorType::Rgb => rgba.extend([pixel[0], pixel[1], pixel[2], 255]),
+                ColorType::Rgba => rgba.extend(pixel),
+                ColorType::Indexed => {
+                    return Err(Error::UnsupportedImageFormat(
+                        "Unexpected indexed APNG frame".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Encodes an image as an 8-bit RGB PNG in memory
+    fn encode(image: &dyn Image) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        write(image, &mut bytes).unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let image = ImageRgb {
+            bytes: vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255],
+            width: 2,
+            height: 2,
+            line_size: 6,
+        };
+
+        let decoded = from_bytes(&encode(&image)).unwrap();
+
+        assert_eq!((decoded.width(), decoded.height()), (2, 2));
+
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            assert_eq!(decoded.pixel(x, y), image.pixel(x, y));
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        let image = ImageRgb {
+            bytes: vec![0; 3 * 16 * 16],
+            width: 16,
+            height: 16,
+            line_size: 3 * 16,
+        };
+
+        let bytes = encode(&image);
+
+        assert!(from_bytes(&bytes[..bytes.len() / 2]).is_err());
+        assert!(from_bytes(&bytes[..8]).is_err());
+        assert!(
+            frames_from_bytes(bytes[..bytes.len() / 2].to_vec())
+                .and_then(|mut frames| frames.next().transpose())
+                .is_err()
+        );
     }
 }
@@ -0,0 +1,186 @@
+use std::fs;
+
+use super::{Image, gray::ImageGray, rgb::ImageRgb};
+use crate::Error;
+
+// Supports the ASCII (P2/P3) and binary (P5/P6) variants of PGM and PPM.  Sample
+// values are rescaled to 8 bits if the maximum sample value is not 255.
+pub fn open(file: &str) -> Result<Box<dyn Image>, Error> {
+    from_bytes(&fs::read(file)?)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Box<dyn Image>, Error> {
+    let mut parser = Parser { bytes, offset: 0 };
+
+    let (channels, binary) = match parser.bytes.get(0..2) {
+        Some(b"P2") => (1, false),
+        Some(b"P3") => (3, false),
+        Some(b"P5") => (1, true),
+        Some(b"P6") => (3, true),
+        _ => {
+            return Err(Error::UnsupportedImageFormat(
+                "Not a PGM or PPM image".to_string(),
+            ));
+        }
+    };
+
+    parser.offset = 2;
+
+    let width = parser.header_value()?;
+    let height = parser.header_value()?;
+    let max_value = parser.header_value()?;
+
+    if width == 0 || height == 0 || max_value == 0 || max_value > 65535 {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "Invalid PNM header (width {}, height {}, maximum value {})",
+            width, height, max_value
+        )));
+    }
+
+    let num_samples = usize::try_from(width)
+        .ok()
+        .and_then(|width| width.checked_mul(usize::try_from(height).ok()?))
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or_else(|| Error::UnsupportedImageFormat("PNM image too large".to_string()))?;
+
+    let samples = if binary {
+        // Exactly one whitespace character separates the header from the raster
+        parser.offset += 1;
+
+        let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+
+        let raster = parser
+            .bytes
+            .get(parser.offset..)
+            .and_then(|raster| raster.get(..num_samples.checked_mul(bytes_per_sample)?))
+            .ok_or_else(|| Error::UnsupportedImageFormat("Truncated PNM image".to_string()))?;
+
+        if bytes_per_sample == 1 {
+            raster.iter().map(|sample| u32::from(*sample)).collect()
+        } else {
+            raster
+                .chunks_exact(2)
+                .map(|sample| u32::from(u16::from_be_bytes([sample[0], sample[1]])))
+                .collect()
+        }
+    } else {
+        // Every sample but the last takes at least a digit and a separator, so the rest
+        // of the input bounds what is worth allocating for a header that may be lying
+        let mut samples = Vec::with_capacity(num_samples.min(bytes.len() - parser.offset));
+
+        for _ in 0..num_samples {
+            samples.push(parser.header_value()?);
+        }
+
+        samples
+    };
+
+    let bytes = if max_value == 255 {
+        samples
+            .into_iter()
+            .map(|sample| u8::try_from(sample.min(255)).unwrap())
+            .collect::<Vec<_>>()
+    } else {
+        samples
+            .into_iter()
+            .map(|sample| {
+                u8::try_from((sample.min(max_value) * 255 + max_value / 2) / max_value).unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if channels == 1 {
+        Ok(Box::new(ImageGray {
+            bytes,
+            width,
+            height,
+            line_size: usize::try_from(width).unwrap(),
+            bytes_per_pixel: 1,
+        }))
+    } else {
+        Ok(Box::new(ImageRgb {
+            bytes,
+            width,
+            height,
+            line_size: 3 * usize::try_from(width).unwrap(),
+        }))
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Parser<'_> {
+    // Parses a decimal number, skipping any preceding whitespace and comments
+    fn header_value(&mut self) -> Result<u32, Error> {
+        loop {
+            match self.bytes.get(self.offset) {
+                Some(b'#') => {
+                    while let Some(byte) = self.bytes.get(self.offset)
+                        && *byte != b'\n'
+                    {
+                        self.offset += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => self.offset += 1,
+                _ => break,
+            }
+        }
+
+        let start = self.offset;
+
+        while let Some(byte) = self.bytes.get(self.offset)
+            && byte.is_ascii_digit()
+        {
+            self.offset += 1;
+        }
+
+        str::from_utf8(&self.bytes[start..self.offset])
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| {
+                Error::UnsupportedImageFormat(format!(
+                    "Malformed PNM image (expected a number at offset {})",
+                    start
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let image = from_bytes(b"P3\n# comment\n2 1\n15\n15 0 0  0 15 15\n").unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(0, 0), (255, 0, 0));
+        assert_eq!(image.pixel(1, 0), (0, 255, 255));
+
+        let image = from_bytes(b"P5 2 1 255\n\x10\x20").unwrap();
+
+        assert_eq!(image.pixel(0, 0), (16, 16, 16));
+        assert_eq!(image.pixel(1, 0), (32, 32, 32));
+    }
+
+    #[test]
+    fn huge_dimensions() {
+        assert!(from_bytes(b"P3 200000 200000 255 1 2 3").is_err());
+        assert!(from_bytes(b"P6 4294967295 4294967295 65535\n\0\0").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(from_bytes(b"P3 0 1 255").is_err());
+        assert!(from_bytes(b"P3 1 1 0 0 0 0").is_err());
+        assert!(from_bytes(b"P3 1 1 255 0 0").is_err());
+        assert!(from_bytes(b"P3 1 1 255 0 x 0").is_err());
+        assert!(from_bytes(b"P6 2 1 255\n\0\0\0").is_err());
+        assert!(from_bytes(b"P7 1 1 255\n\0").is_err());
+        assert!(from_bytes(b"P2 99999999999 1 255 0").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use super::Image;
+use crate::Error;
+
+// A rectangular region of another image, with coordinates relative to the top left
+// corner of the region
+pub struct View<'a, T: Image + ?Sized> {
+    pub inner: &'a T,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl<'a, T: Image + ?Sized> View<'a, T> {
+    pub fn new(inner: &'a T, x: u32, y: u32, width: u32, height: u32) -> Result<Self, Error> {
+        if x.checked_add(width)
+            .is_none_or(|right| right > inner.width())
+            || y.checked_add(height)
+                .is_none_or(|bottom| bottom > inner.height())
+        {
+            return Err(Error::InvalidRegion(format!(
+                "View {}x{}+{}+{} exceeds image dimensions {}x{}",
+                width,
+                height,
+                x,
+                y,
+                inner.width(),
+                inner.height()
+            )));
+        }
+
+        Ok(Self {
+            inner,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    pub fn to_parent(&self, x: u32, y: u32) -> (u32, u32) {
+        (self.x + x, self.y + y)
+    }
+
+    pub fn from_parent(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        let x = x.checked_sub(self.x)?;
+        let y = y.checked_sub(self.y)?;
+
+        if x < self.width && y < self.height {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Image + ?Sized> Image for View<'_, T> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+        self.inner.pixel(self.x + x, self.y + y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::rgb::ImageRgb;
+
+    #[test]
+    fn region_out_of_bounds() {
+        let image = ImageRgb {
+            bytes: vec![0; 3 * 4 * 4],
+            width: 4,
+            height: 4,
+            line_size: 3 * 4,
+        };
+
+        assert!(View::new(&image, 1, 1, 3, 3).is_ok());
+        assert!(matches!(
+            View::new(&image, 1, 1, 4, 3),
+            Err(Error::InvalidRegion(_))
+        ));
+        assert!(matches!(
+            View::new(&image, 0, u32::MAX, 1, 1),
+            Err(Error::InvalidRegion(_))
+        ));
+    }
+}
@@ -4,13 +4,19 @@ use x11rb::{
 };
 
 use super::{Image, bgra::ImageBgra};
+use crate::Error;
 
-pub fn from_window(conn: &RustConnection, window: u32) -> Result<Box<dyn Image>, String> {
+pub fn from_window(conn: &RustConnection, window: u32) -> Result<Box<dyn Image>, Error> {
     let geometry = conn
         .get_geometry(window)
-        .unwrap()
+        .map_err(|err| Error::X11(err.to_string()))?
         .reply()
-        .map_err(|_| format!("Error retrieving geometry for window {:08x}", window))?;
+        .map_err(|_| {
+            Error::X11(format!(
+                "Error retrieving geometry for window {:08x}",
+                window
+            ))
+        })?;
 
     let width = geometry.width;
     let height = geometry.height;
@@ -25,9 +31,14 @@ pub fn from_window(conn: &RustConnection, window: u32) -> Result<Box<dyn Image>,
             height,
             0xffffffff,
         )
-        .unwrap()
+        .map_err(|err| Error::X11(err.to_string()))?
         .reply()
-        .map_err(|_| format!("Error retrieving contents of window {:08x}", window))?;
+        .map_err(|_| {
+            Error::X11(format!(
+                "Error retrieving contents of window {:08x}",
+                window
+            ))
+        })?;
 
     Ok(Box::new(ImageBgra {
         bytes: image.data,
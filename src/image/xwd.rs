@@ -0,0 +1,277 @@
+use std::{collections::BTreeMap, fs};
+
+use super::{Image, bgra::ImageBgra, check_masks, extract_channel, rgb::ImageRgb};
+use crate::Error;
+
+const XWD_FILE_VERSION: u32 = 7;
+const XWD_HEADER_SIZE: usize = 100;
+const XWD_COLOR_SIZE: usize = 12;
+
+const Z_PIXMAP: u32 = 2;
+
+const LSB_FIRST: u32 = 0;
+
+const STATIC_GRAY: u32 = 0;
+const GRAY_SCALE: u32 = 1;
+const STATIC_COLOR: u32 = 2;
+const PSEUDO_COLOR: u32 = 3;
+
+// Returns whether the given bytes look like the start of an XWD file.  XWD files
+// have no magic number, but the file version field is a decent substitute.
+pub fn is_xwd(bytes: &[u8]) -> bool {
+    matches!(bytes.get(4..8), Some([0, 0, 0, 7]) | Some([7, 0, 0, 0]))
+}
+
+// Supports ZPixmap images at 8, 16, 24 and 32 bits per pixel in either byte order,
+// as written by xwd(1).  32 bits per pixel little endian images with the usual X
+// visual masks are used as-is, everything else is converted to RGB.
+pub fn open(file: &str) -> Result<Box<dyn Image>, Error> {
+    from_bytes(&fs::read(file)?)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Box<dyn Image>, Error> {
+    // The header is written in the byte order of the machine that wrote the file
+    let header_big_endian = match bytes.get(4..8) {
+        Some([0, 0, 0, 7]) => true,
+        Some([7, 0, 0, 0]) => false,
+        _ => {
+            return Err(Error::UnsupportedImageFormat(
+                "Not an XWD image".to_string(),
+            ));
+        }
+    };
+
+    let header = (0..XWD_HEADER_SIZE / 4)
+        .map(|i| read_u32(bytes, 4 * i, header_big_endian))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let header_size = usize::try_from(header[0]).unwrap();
+    let file_version = header[1];
+    let pixmap_format = header[2];
+    let width = header[4];
+    let height = header[5];
+    let byte_order = header[7];
+    let bits_per_pixel = header[11];
+    let bytes_per_line = usize::try_from(header[12]).unwrap();
+    let visual_class = header[13];
+    let masks = [header[14], header[15], header[16]];
+    let num_colors = usize::try_from(header[19]).unwrap();
+
+    if file_version != XWD_FILE_VERSION || header_size < XWD_HEADER_SIZE {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "Unsupported XWD file version {} and/or header size {}",
+            file_version, header_size
+        )));
+    }
+
+    if pixmap_format != Z_PIXMAP {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "Unsupported XWD pixmap format {}",
+            pixmap_format
+        )));
+    }
+
+    if !matches!(bits_per_pixel, 8 | 16 | 24 | 32) {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "Unsupported XWD bit depth {}",
+            bits_per_pixel
+        )));
+    }
+
+    let bytes_per_pixel = usize::try_from(bits_per_pixel / 8).unwrap();
+    let width_usize = usize::try_from(width).unwrap();
+    let height_usize = usize::try_from(height).unwrap();
+
+    if bytes_per_line == 0 || bytes_per_line < bytes_per_pixel * width_usize {
+        return Err(Error::UnsupportedImageFormat(format!(
+            "XWD line size {} too small for width {}",
+            bytes_per_line, width
+        )));
+    }
+
+    let mut colors = BTreeMap::new();
+
+    let colors_offset = header_size;
+
+    for i in 0..num_colors {
+        let offset = colors_offset + XWD_COLOR_SIZE * i;
+
+        let pixel = read_u32(bytes, offset, header_big_endian)?;
+        let red = read_u16(bytes, offset + 4, header_big_endian)?;
+        let green = read_u16(bytes, offset + 6, header_big_endian)?;
+        let blue = read_u16(bytes, offset + 8, header_big_endian)?;
+
+        colors.insert(
+            pixel,
+            [red, green, blue].map(|channel| channel.to_be_bytes()[0]),
+        );
+    }
+
+    let data_offset = colors_offset + XWD_COLOR_SIZE * num_colors;
+
+    let data = bytes_per_line
+        .checked_mul(height_usize)
+        .and_then(|size| bytes.get(data_offset..)?.get(..size))
+        .ok_or_else(|| Error::UnsupportedImageFormat("Truncated XWD image".to_string()))?;
+
+    if bits_per_pixel == 32
+        && byte_order == LSB_FIRST
+        && masks == [0x00ff0000, 0x0000ff00, 0x000000ff]
+    {
+        return Ok(Box::new(ImageBgra {
+            bytes: data.to_vec(),
+            width,
+            height,
+            line_size: bytes_per_line,
+        }));
+    }
+
+    let indexed = matches!(
+        visual_class,
+        STATIC_GRAY | GRAY_SCALE | STATIC_COLOR | PSEUDO_COLOR
+    );
+
+    if !indexed {
+        check_masks(masks, "XWD")?;
+    }
+
+    let mut rgb = Vec::with_capacity(3 * width_usize * height_usize);
+
+    for row in data.chunks_exact(bytes_per_line) {
+        for pixel in row[..bytes_per_pixel * width_usize].chunks_exact(bytes_per_pixel) {
+            let value = pixel.iter().enumerate().fold(0u32, |value, (i, byte)| {
+                if byte_order == LSB_FIRST {
+                    value | (u32::from(*byte) << (8 * i))
+                } else {
+                    (value << 8) | u32::from(*byte)
+                }
+            });
+
+            if indexed {
+                let Some(color) = colors.get(&value) else {
+                    return Err(Error::UnsupportedImageFormat(format!(
+                        "XWD pixel value {} not in colormap",
+                        value
+                    )));
+                };
+
+                rgb.extend(color);
+            } else {
+                rgb.extend(masks.map(|mask| extract_channel(value, mask)));
+            }
+        }
+    }
+
+    Ok(Box::new(ImageRgb {
+        bytes: rgb,
+        width,
+        height,
+        line_size: 3 * width_usize,
+    }))
+}
+
+fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> Result<u16, Error> {
+    let b = bytes
+        .get(offset..offset + 2)
+        .ok_or_else(|| Error::UnsupportedImageFormat("Truncated XWD image".to_string()))?;
+
+    Ok(if big_endian {
+        u16::from_be_bytes([b[0], b[1]])
+    } else {
+        u16::from_le_bytes([b[0], b[1]])
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Result<u32, Error> {
+    let b = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| Error::UnsupportedImageFormat("Truncated XWD image".to_string()))?;
+
+    Ok(if big_endian {
+        u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+    } else {
+        u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A big endian header for a 32 bits per pixel TrueColor ZPixmap image
+    fn xwd_32(width: u32, height: u32, byte_order: u32, data: &[u8]) -> Vec<u8> {
+        let mut header = [0u32; XWD_HEADER_SIZE / 4];
+
+        header[0] = 100;
+        header[1] = XWD_FILE_VERSION;
+        header[2] = Z_PIXMAP;
+        header[4] = width;
+        header[5] = height;
+        header[7] = byte_order;
+        header[11] = 32;
+        header[12] = 4 * width;
+        header[13] = 4;
+        header[14..17].copy_from_slice(&[0x00ff0000, 0x0000ff00, 0x000000ff]);
+
+        let mut bytes = header
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        bytes.extend(data);
+
+        bytes
+    }
+
+    #[test]
+    fn decode() {
+        let data = [0, 1, 2, 3, 0, 4, 5, 6];
+
+        let image = from_bytes(&xwd_32(2, 1, 1, &data)).unwrap();
+
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(0, 0), (1, 2, 3));
+        assert_eq!(image.pixel(1, 0), (4, 5, 6));
+
+        let image = from_bytes(&xwd_32(2, 1, LSB_FIRST, &data)).unwrap();
+
+        assert_eq!(image.pixel(0, 0), (2, 1, 0));
+    }
+
+    #[test]
+    fn huge_dimensions() {
+        assert!(from_bytes(&xwd_32(u32::MAX / 4, u32::MAX, 1, &[0; 16])).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        let bytes = xwd_32(2, 2, 1, &[0; 16]);
+
+        assert!(from_bytes(&bytes).is_ok());
+
+        // Truncated pixel data and header
+        assert!(from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_bytes(&bytes[..XWD_HEADER_SIZE - 1]).is_err());
+
+        // Line size too small for the width
+        let mut bytes = bytes;
+
+        bytes[48..52].copy_from_slice(&4u32.to_be_bytes());
+
+        assert!(from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn malformed_masks() {
+        for mask in [0b101u32, 0, 0x80000001] {
+            let mut bytes = xwd_32(1, 1, 1, &[0xff; 4]);
+
+            bytes[56..60].copy_from_slice(&mask.to_be_bytes());
+
+            assert!(matches!(
+                from_bytes(&bytes),
+                Err(Error::UnsupportedImageFormat(_))
+            ));
+        }
+    }
+}
@@ -1,9 +1,20 @@
+pub mod board;
 mod bounds;
-mod color_scheme;
+pub mod color_scheme;
+pub mod detection;
+pub mod detector;
 pub mod edgemap;
+mod error;
 pub mod grid;
 pub mod image;
+pub mod overlay;
 mod pixels;
+pub mod screen;
 mod segment;
+mod shape;
 pub mod tile;
 mod trail;
+
+pub use bounds::Bounds;
+pub use error::Error;
+pub use segment::Connectivity;
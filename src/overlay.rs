@@ -0,0 +1,212 @@
+use crate::{bounds::Bounds, grid::Grid, image::Image, pixels::Pixels, tile::Tile};
+
+// Colors for the masks of the seven shape color classes, in
+// ColorScheme::shape_colors() order.  Outer colors are the brighter variants.
+const CLASS_COLORS: [[u8; 3]; 7] = [
+    [255, 96, 96],
+    [160, 32, 32],
+    [96, 255, 96],
+    [32, 160, 32],
+    [96, 160, 255],
+    [32, 64, 192],
+    [255, 224, 32],
+];
+
+const BOUNDS_COLOR: [u8; 3] = [255, 255, 255];
+const TILE_COLOR: [u8; 3] = [255, 0, 255];
+const GRID_COLOR: [u8; 3] = [0, 255, 255];
+const TEXT_COLOR: [u8; 3] = [255, 255, 255];
+
+// 3x5 glyphs for the digits 0-9, row by row from the top, three bits per row with
+// the most significant bit on the left
+const DIGITS: [[u8; 5]; 10] = [
+    [7, 5, 5, 5, 7],
+    [2, 6, 2, 2, 7],
+    [7, 1, 7, 4, 7],
+    [7, 1, 7, 1, 7],
+    [5, 5, 7, 1, 1],
+    [7, 4, 7, 1, 7],
+    [7, 4, 7, 5, 7],
+    [7, 1, 1, 1, 1],
+    [7, 5, 7, 5, 7],
+    [7, 5, 7, 1, 7],
+];
+
+// Each glyph pixel is drawn as a square of this many pixels
+const GLYPH_SCALE: u32 = 2;
+
+// A debugging aid showing what tile detection saw: a dimmed grayscale copy of the
+// image, with the color masks, segment bounding boxes, connector hole counts, tile
+// centers and grid lines drawn on top.  Save it with image::png::save().
+pub struct Overlay {
+    bytes: Vec<u8>,
+    width: u32,
+    height: u32,
+
+    // Offset added to the coordinates passed in by tile detection, for detection
+    // running on a view of the image
+    origin: (u32, u32),
+}
+
+impl Overlay {
+    pub fn new<T: Image + ?Sized>(image: &T) -> Self {
+        let mut bytes = Vec::with_capacity(
+            3 * usize::try_from(image.width()).unwrap() * usize::try_from(image.height()).unwrap(),
+        );
+
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+
+                let gray = (u32::from(p.0) * 30 + u32::from(p.1) * 59 + u32::from(p.2) * 11) / 300;
+
+                bytes.extend([u8::try_from(gray).unwrap(); 3]);
+            }
+        }
+
+        Overlay {
+            bytes,
+            width: image.width(),
+            height: image.height(),
+            origin: (0, 0),
+        }
+    }
+
+    pub(crate) fn set_origin(&mut self, x: u32, y: u32) {
+        self.origin = (x, y);
+    }
+
+    pub(crate) fn mask(&mut self, class: usize, mask: &Pixels) {
+        for (x, y) in mask.iter() {
+            self.plot(u32::from(x), u32::from(y), CLASS_COLORS[class]);
+        }
+    }
+
+    pub(crate) fn bounds(&mut self, bounds: &Bounds) {
+        let color = BOUNDS_COLOR;
+
+        let (min_x, max_x) = (u32::from(bounds.min_x), u32::from(bounds.max_x));
+        let (min_y, max_y) = (u32::from(bounds.min_y), u32::from(bounds.max_y));
+
+        for x in min_x..=max_x {
+            self.plot(x, min_y, color);
+            self.plot(x, max_y, color);
+        }
+
+        for y in min_y..=max_y {
+            self.plot(min_x, y, color);
+            self.plot(max_x, y, color);
+        }
+    }
+
+    // Writes the hole count just above the top left corner of the bounding box
+    pub(crate) fn hole_count(&mut self, bounds: &Bounds, num_holes: usize) {
+        let x = u32::from(bounds.min_x);
+        let y = u32::from(bounds.min_y).saturating_sub(6 * GLYPH_SCALE);
+
+        for (i, digit) in num_holes.to_string().bytes().enumerate() {
+            let glyph = DIGITS[usize::from(digit - b'0')];
+
+            let x = x + u32::try_from(i).unwrap() * 4 * GLYPH_SCALE;
+
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (4 >> column) != 0 {
+                        self.fill(
+                            x + column * GLYPH_SCALE,
+                            y + u32::try_from(row).unwrap() * GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            TEXT_COLOR,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Marks the point that the tile is aligned to the grid by with a small cross
+    pub(crate) fn tile(&mut self, tile: &Tile) {
+        let (x, y) = (u32::from(tile.grid_x), u32::from(tile.grid_y));
+
+        for d in 0..=4 {
+            self.plot(x + d, y, TILE_COLOR);
+            self.plot(x.wrapping_sub(d), y, TILE_COLOR);
+            self.plot(x, y + d, TILE_COLOR);
+            self.plot(x, y.wrapping_sub(d), TILE_COLOR);
+        }
+    }
+
+    // Draws the grid's column and row lines across the whole image, leaving the
+    // tile markers visible.  Unlike the other drawing functions, this takes
+    // coordinates relative to the full image.
+    pub fn grid(&mut self, grid: &Grid) {
+        for x in &grid.column_x {
+            for y in 0..self.height {
+                self.set_unless_tile(u32::from(*x), y, GRID_COLOR);
+            }
+        }
+
+        for y in &grid.row_y {
+            for x in 0..self.width {
+                self.set_unless_tile(x, u32::from(*y), GRID_COLOR);
+            }
+        }
+    }
+
+    fn fill(&mut self, x: u32, y: u32, size: u32, color: [u8; 3]) {
+        for dy in 0..size {
+            for dx in 0..size {
+                self.plot(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    // Sets a pixel given in detection coordinates, ignoring pixels off the image
+    fn plot(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        if let (Some(x), Some(y)) = (x.checked_add(self.origin.0), y.checked_add(self.origin.1)) {
+            self.set(x, y, color);
+        }
+    }
+
+    fn set_unless_tile(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let p = self.pixel(x, y);
+
+            if [p.0, p.1, p.2] != TILE_COLOR {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let index = 3
+                * (usize::try_from(y).unwrap() * usize::try_from(self.width).unwrap()
+                    + usize::try_from(x).unwrap());
+
+            self.bytes[index..index + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+impl Image for Overlay {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+        let index = 3
+            * (usize::try_from(y).unwrap() * usize::try_from(self.width).unwrap()
+                + usize::try_from(x).unwrap());
+
+        (
+            self.bytes[index],
+            self.bytes[index + 1],
+            self.bytes[index + 2],
+        )
+    }
+}
@@ -1,18 +1,68 @@
-use std::collections::{BTreeMap, BTreeSet};
-
 use crate::bounds::Bounds;
 
-#[derive(Debug, Default)]
+// Minimum number of columns and rows by which the bitmap grows when a pixel outside
+// of it is inserted
+const MIN_GROWTH: u32 = 64;
+
+// A set of pixel coordinates, stored as a bitmap covering a rectangular frame.  The
+// frame grows as needed, but for sets that fill a known area it's cheaper to
+// allocate it up front with with_size().
+#[derive(Clone, Debug)]
 pub struct Pixels {
-    pub pixels: BTreeMap<u16, BTreeSet<u16>>,
     pub bounds: Bounds,
+
+    // Bitmap of the frame starting at (x0, y0), stored column by column, with each
+    // column padded to whole words
+    x0: u16,
+    y0: u16,
+    columns: u32,
+    rows: u32,
+    words_per_column: usize,
+    bits: Vec<u64>,
+
+    len: usize,
+}
+
+impl Default for Pixels {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Pixels {
     pub fn new() -> Self {
         Self {
-            pixels: BTreeMap::new(),
             bounds: Bounds::new(),
+            x0: 0,
+            y0: 0,
+            columns: 0,
+            rows: 0,
+            words_per_column: 0,
+            bits: Vec::new(),
+            len: 0,
+        }
+    }
+
+    // Returns an empty set whose bitmap already covers (0, 0) to (width, height)
+    pub fn with_size(width: u32, height: u32) -> Self {
+        Self::with_frame(0, 0, width, height)
+    }
+
+    pub(crate) fn with_frame(x0: u16, y0: u16, columns: u32, rows: u32) -> Self {
+        assert!(u32::from(x0) + columns <= u32::from(u16::MAX) + 1);
+        assert!(u32::from(y0) + rows <= u32::from(u16::MAX) + 1);
+
+        let words_per_column = usize::try_from(rows.div_ceil(64)).unwrap();
+
+        Self {
+            bounds: Bounds::new(),
+            x0,
+            y0,
+            columns,
+            rows,
+            words_per_column,
+            bits: vec![0; words_per_column * usize::try_from(columns).unwrap()],
+            len: 0,
         }
     }
 
@@ -24,19 +74,179 @@ impl Pixels {
         let x = x.try_into().unwrap();
         let y = y.try_into().unwrap();
 
-        let inserted = self.pixels.entry(x).or_default().insert(y);
+        if !self.in_frame(x, y) {
+            self.grow(x, y);
+        }
+
+        let (word, bit) = self.position(x, y);
+
+        let inserted = self.bits[word] & bit == 0;
 
         if inserted {
+            self.bits[word] |= bit;
+            self.len += 1;
             self.bounds.update(x, y);
         }
 
         inserted
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        if self.in_frame(x, y) {
+            let (word, bit) = self.position(x, y);
+
+            self.bits[word] & bit != 0
+        } else {
+            false
+        }
+    }
+
     pub fn is_within(&self, other: &Self) -> bool {
         self.bounds.is_within(&other.bounds)
     }
 
+    // Iterates over the pixels column by column, i.e. ordered by x and then by y
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.bits
+            .chunks_exact(self.words_per_column.max(1))
+            .take(usize::try_from(self.columns).unwrap())
+            .enumerate()
+            .flat_map(move |(column, words)| {
+                let x = self.x0 + u16::try_from(column).unwrap();
+
+                words.iter().enumerate().flat_map(move |(i, word)| {
+                    let mut word = *word;
+
+                    std::iter::from_fn(move || {
+                        if word == 0 {
+                            return None;
+                        }
+
+                        let bit = usize::try_from(word.trailing_zeros()).unwrap();
+
+                        word &= word - 1;
+
+                        Some((x, self.y0 + u16::try_from(64 * i + bit).unwrap()))
+                    })
+                })
+            })
+    }
+
+    pub(crate) fn remove(&mut self, x: u16, y: u16) -> bool {
+        if !self.in_frame(x, y) {
+            return false;
+        }
+
+        let (word, bit) = self.position(x, y);
+
+        let removed = self.bits[word] & bit != 0;
+
+        if removed {
+            self.bits[word] &= !bit;
+            self.len -= 1;
+
+            // self.bounds is now invalid
+        }
+
+        removed
+    }
+
+    // Returns the first pixel in column order at or after the given word of the
+    // bitmap, advancing the word index past any empty words
+    pub(crate) fn next_pixel(&self, word: &mut usize) -> Option<(u16, u16)> {
+        while let Some(bits) = self.bits.get(*word) {
+            if *bits != 0 {
+                let column = *word / self.words_per_column;
+                let row = 64 * (*word % self.words_per_column)
+                    + usize::try_from(bits.trailing_zeros()).unwrap();
+
+                return Some((
+                    self.x0 + u16::try_from(column).unwrap(),
+                    self.y0 + u16::try_from(row).unwrap(),
+                ));
+            }
+
+            *word += 1;
+        }
+
+        None
+    }
+
+    fn in_frame(&self, x: u16, y: u16) -> bool {
+        x >= self.x0
+            && y >= self.y0
+            && u32::from(x - self.x0) < self.columns
+            && u32::from(y - self.y0) < self.rows
+    }
+
+    // Returns the index of the word containing the pixel, and the pixel's bit in it
+    fn position(&self, x: u16, y: u16) -> (usize, u64) {
+        let dy = usize::from(y - self.y0);
+
+        (
+            usize::from(x - self.x0) * self.words_per_column + dy / 64,
+            1 << (dy % 64),
+        )
+    }
+
+    // Enlarges the frame to include the given pixel, with some slack so that
+    // inserting pixel by pixel doesn't reallocate every time
+    fn grow(&mut self, x: u16, y: u16) {
+        let (x, y) = (u32::from(x), u32::from(y));
+        let limit = u32::from(u16::MAX) + 1;
+
+        let empty = self.columns == 0 || self.rows == 0;
+
+        let (mut x0, mut y0, mut x1, mut y1) = if empty {
+            (x, y, x + 1, y + 1)
+        } else {
+            let (x0, y0) = (u32::from(self.x0), u32::from(self.y0));
+
+            (x0, y0, x0 + self.columns, y0 + self.rows)
+        };
+
+        let slack_x = self.columns.max(MIN_GROWTH);
+        let slack_y = self.rows.max(MIN_GROWTH);
+
+        if empty || x < x0 {
+            x0 = x.min(x0).saturating_sub(slack_x);
+        }
+
+        if empty || x >= x1 {
+            x1 = ((x + 1).max(x1) + slack_x).min(limit);
+        }
+
+        if empty || y < y0 {
+            y0 = y.min(y0).saturating_sub(slack_y);
+        }
+
+        if empty || y >= y1 {
+            y1 = ((y + 1).max(y1) + slack_y).min(limit);
+        }
+
+        let mut grown = Self::with_frame(
+            u16::try_from(x0).unwrap(),
+            u16::try_from(y0).unwrap(),
+            x1 - x0,
+            y1 - y0,
+        );
+
+        for (x, y) in self.iter() {
+            let (word, bit) = grown.position(x, y);
+
+            grown.bits[word] |= bit;
+        }
+
+        grown.len = self.len;
+        grown.bounds = self.bounds;
+
+        *self = grown;
+    }
+
     /*
     pub fn write_png(&self, file: &str, width: u32, height: u32) {
         use std::{fs::File, io::BufWriter};
@@ -57,9 +267,7 @@ impl Pixels {
 
         for y in 0..u16::try_from(height).unwrap() {
             for x in 0..u16::try_from(width).unwrap() {
-                if let Some(ys) = self.pixels.get(&x)
-                    && ys.contains(&y)
-                {
+                if self.contains(x, y) {
                     bytes.extend(&[255, 255, 255]);
                 } else {
                     bytes.extend(&[0, 0, 0]);
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use crate::{
+    board::Board,
+    color_scheme::{ColorScheme, color_distance},
+    detector::ColorTableDetector,
+    edgemap::EdgeMap,
+    image::Image,
+    tile::DetectOptions,
+};
+
+// At least this fraction of the game area of a puzzle screen is background, around
+// and between the tiles
+const MIN_BACKGROUND_FRACTION: u64 = 4;
+
+// What a screenshot of LYNE shows
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Screen {
+    // A board that still has paths to be drawn, with its level key
+    InProgress(String),
+
+    // A board whose paths are all drawn
+    Solved,
+
+    // A board whose tiles can't be read, such as during the animation that leads
+    // from a solved board to the next level, in which tiles change color
+    Transition,
+
+    // Anything without a board in one of the color schemes, such as a menu
+    NotPuzzle,
+}
+
+impl Screen {
+    // Classifies a screenshot by first looking for a game area with the border and
+    // background colors of one of the color schemes, and then at the tiles and paths
+    // within it
+    pub fn classify<T: Image + ?Sized>(image: &T, options: &DetectOptions) -> Self {
+        let Ok(board) = Board::locate(image, options) else {
+            return Screen::NotPuzzle;
+        };
+
+        let Ok(color_scheme) = board.detect_color_scheme(image, options) else {
+            return Screen::NotPuzzle;
+        };
+
+        if background_area(image, &board, &color_scheme, options) * MIN_BACKGROUND_FRACTION
+            < u64::from(board.width) * u64::from(board.height)
+        {
+            return Screen::NotPuzzle;
+        }
+
+        let detector = ColorTableDetector {
+            color_scheme: Some(&color_scheme),
+            options: *options,
+        };
+
+        let Ok(interpretation) = board
+            .detect(image, &detector, None)
+            .and_then(|detection| detection.solve())
+        else {
+            return Screen::Transition;
+        };
+
+        let grid = &interpretation.grid;
+
+        let drawn = EdgeMap::detect_drawn(image, grid, &color_scheme, options);
+
+        // Solved only if every edge of a solution is drawn
+        match EdgeMap::solve_from(grid, &drawn) {
+            Ok(solution) if solution.edgemap.is_subset_of(&drawn) => Screen::Solved,
+            _ => Screen::InProgress(grid.level_key()),
+        }
+    }
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Screen::InProgress(level_key) => write!(f, "board {} in progress", level_key),
+            Screen::Solved => write!(f, "board solved"),
+            Screen::Transition => write!(f, "board changing levels"),
+            Screen::NotPuzzle => write!(f, "not a puzzle screen"),
+        }
+    }
+}
+
+// Counts the pixels of the game area that have the background or empty cell color
+fn background_area<T: Image + ?Sized>(
+    image: &T,
+    board: &Board,
+    color_scheme: &ColorScheme,
+    options: &DetectOptions,
+) -> u64 {
+    let mut area = 0;
+
+    for y in board.y..board.y + board.height {
+        for x in board.x..board.x + board.width {
+            let p = image.pixel(x, y);
+
+            if [color_scheme.background_color, color_scheme.empty_cell_color]
+                .iter()
+                .any(|color| {
+                    color_distance(&[p.0, p.1, p.2], color)
+                        <= options.color_tolerance.saturating_pow(2)
+                })
+            {
+                area += 1;
+            }
+        }
+    }
+
+    area
+}
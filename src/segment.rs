@@ -1,111 +1,300 @@
-use crate::pixels::Pixels;
+use std::fmt;
+
+use crate::{bounds::Bounds, pixels::Pixels};
+
+// Which neighbors of a pixel count as connected to it: only the 4 that share an
+// edge with it, or also the 4 diagonal ones
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Connectivity {
+    #[default]
+    Four,
+    Eight,
+}
+
+impl Connectivity {
+    // The connectivity to use for the background of segments with this
+    // connectivity.  Using the same connectivity for both would let diagonal gaps
+    // both connect and separate, and holes wouldn't be counted consistently.
+    pub fn dual(self) -> Self {
+        match self {
+            Connectivity::Four => Connectivity::Eight,
+            Connectivity::Eight => Connectivity::Four,
+        }
+    }
+}
+
+impl fmt::Display for Connectivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Connectivity::Four => write!(f, "4-connectivity"),
+            Connectivity::Eight => write!(f, "8-connectivity"),
+        }
+    }
+}
 
 impl Pixels {
+    // Splits the pixels into 4-connected segments, ordered by their first pixel in
+    // column order, leaving self empty
     pub fn segment_all(&mut self) -> Vec<Pixels> {
+        self.segment_all_with(Connectivity::Four)
+    }
+
+    pub fn segment_all_with(&mut self, connectivity: Connectivity) -> Vec<Pixels> {
         let mut segments = Vec::new();
 
-        while let Some((x, ys)) = self.pixels.first_key_value() {
-            let y = ys.first().unwrap();
+        let mut word = 0;
 
-            segments.push(self.segment(*x, *y));
+        while let Some((x, y)) = self.next_pixel(&mut word) {
+            segments.push(self.segment(x, y, connectivity));
         }
 
         segments
     }
 
-    fn segment(&mut self, x: u16, y: u16) -> Pixels {
-        let mut segment = Pixels::new();
+    // Scanline flood fill along columns: removes the vertical run through each seed
+    // pixel, then seeds the runs next to it in the neighboring columns
+    fn segment(&mut self, x: u16, y: u16, connectivity: Connectivity) -> Pixels {
+        let mut runs = Vec::new();
+        let mut bounds = Bounds::new();
 
-        let mut expand_set = Pixels::new();
+        let mut seeds = vec![(x, y)];
 
-        assert!(self.remove(x, y));
-        expand_set.insert(x, y);
+        while let Some((x, y)) = seeds.pop() {
+            if !self.remove(x, y) {
+                continue;
+            }
 
-        while let Some((x, y)) = expand_set.pop_first() {
-            assert!(segment.insert(x, y));
+            let mut min_y = y;
 
-            let neighbors = {
-                let mut neighbors = Vec::with_capacity(4);
+            while min_y > 0 && self.remove(x, min_y - 1) {
+                min_y -= 1;
+            }
 
-                if x > 0 {
-                    neighbors.push((x - 1, y));
-                }
+            let mut max_y = y;
 
-                if x < u16::MAX {
-                    neighbors.push((x + 1, y));
-                }
+            while max_y < u16::MAX && self.remove(x, max_y + 1) {
+                max_y += 1;
+            }
 
-                if y > 0 {
-                    neighbors.push((x, y - 1));
-                }
+            bounds.update(x, min_y);
+            bounds.update(x, max_y);
 
-                if y < u16::MAX {
-                    neighbors.push((x, y + 1));
-                }
+            runs.push((x, min_y, max_y));
 
-                neighbors
+            // With 8-connectivity, runs in the neighboring columns may also touch this
+            // one diagonally at either end
+            let (scan_min_y, scan_max_y) = match connectivity {
+                Connectivity::Four => (min_y, max_y),
+                Connectivity::Eight => (min_y.saturating_sub(1), max_y.saturating_add(1)),
             };
 
-            for (x, y) in neighbors {
-                if self.remove(x, y) {
-                    expand_set.insert(x, y);
+            for x in [x.checked_sub(1), x.checked_add(1)].into_iter().flatten() {
+                let mut in_run = false;
+
+                for y in scan_min_y..=scan_max_y {
+                    let contained = self.contains(x, y);
+
+                    if contained && !in_run {
+                        seeds.push((x, y));
+                    }
+
+                    in_run = contained;
                 }
             }
         }
 
+        let mut segment = Pixels::with_frame(
+            bounds.min_x,
+            bounds.min_y,
+            u32::from(bounds.max_x - bounds.min_x) + 1,
+            u32::from(bounds.max_y - bounds.min_y) + 1,
+        );
+
+        for (x, min_y, max_y) in runs {
+            for y in min_y..=max_y {
+                segment.insert(x, y);
+            }
+        }
+
         segment
     }
 
-    fn remove(&mut self, x: u16, y: u16) -> bool {
-        if let Some(ys) = self.pixels.get_mut(&x)
-            && ys.remove(&y)
-        {
-            if ys.is_empty() {
-                self.pixels.remove(&x);
+    // Counts the regions of the background that are completely enclosed by the
+    // pixels, given the connectivity of the pixels themselves.  The background is
+    // segmented with the dual connectivity.
+    pub fn num_holes(&self, connectivity: Connectivity) -> usize {
+        self.holes(connectivity).len()
+    }
+
+    // Like num_holes, but returns the pixels of each hole
+    pub fn holes(&self, connectivity: Connectivity) -> Vec<Pixels> {
+        if self.len() == 0 {
+            return Vec::new();
+        }
+
+        let bounds = self.bounds;
+
+        let mut invert = Pixels::with_frame(
+            bounds.min_x,
+            bounds.min_y,
+            u32::from(bounds.max_x - bounds.min_x) + 1,
+            u32::from(bounds.max_y - bounds.min_y) + 1,
+        );
+
+        for x in bounds.min_x..=bounds.max_x {
+            for y in bounds.min_y..=bounds.max_y {
+                if !self.contains(x, y) {
+                    invert.insert(x, y);
+                }
             }
+        }
 
-            // self.bounds is now invalid
+        // Background regions touching the bounding box are connected to the outside
+        invert
+            .segment_all_with(connectivity.dual())
+            .into_iter()
+            .filter(|region| region.is_within(self))
+            .collect()
+    }
 
-            true
-        } else {
-            false
+    // Removes the parts that a square of 2 * radius + 1 pixels doesn't fit into, such
+    // as lines thinner than that, and keeps the rest as it is.  This is an erosion
+    // followed by a dilation, each done along rows and then along columns.
+    pub fn opened(&self, radius: u16) -> Pixels {
+        self.eroded(radius, (1, 0))
+            .eroded(radius, (0, 1))
+            .dilated(radius, (1, 0))
+            .dilated(radius, (0, 1))
+    }
+
+    // Keeps the pixels whose neighbors up to the radius away in both directions
+    // along the axis are all in the set as well
+    fn eroded(&self, radius: u16, axis: (u16, u16)) -> Pixels {
+        let mut eroded = Pixels::new();
+
+        for (x, y) in self.iter() {
+            if (1..=radius).all(|d| {
+                let (dx, dy) = (d * axis.0, d * axis.1);
+
+                x.checked_sub(dx)
+                    .zip(y.checked_sub(dy))
+                    .is_some_and(|(x, y)| self.contains(x, y))
+                    && x.checked_add(dx)
+                        .zip(y.checked_add(dy))
+                        .is_some_and(|(x, y)| self.contains(x, y))
+            }) {
+                eroded.insert(x, y);
+            }
         }
+
+        eroded
     }
 
-    fn pop_first(&mut self) -> Option<(u16, u16)> {
-        if let Some((x, ys)) = self.pixels.first_key_value() {
-            let x = *x;
-            let y = *ys.first().unwrap();
+    // Adds the pixels up to the radius away from any pixel in the set along the axis
+    fn dilated(&self, radius: u16, axis: (u16, u16)) -> Pixels {
+        let mut dilated = self.clone();
+
+        for (x, y) in self.iter() {
+            for d in 1..=radius {
+                let (dx, dy) = (d * axis.0, d * axis.1);
 
-            self.remove(x, y);
+                if let (Some(x), Some(y)) = (x.checked_sub(dx), y.checked_sub(dy)) {
+                    dilated.insert(x, y);
+                }
 
-            Some((x, y))
-        } else {
-            None
+                if let (Some(x), Some(y)) = (x.checked_add(dx), y.checked_add(dy)) {
+                    dilated.insert(x, y);
+                }
+            }
         }
+
+        dilated
     }
 
-    pub fn num_holes(&self) -> usize {
-        let mut invert = Pixels::new();
+    // The number of connected components minus the number of holes, which for a
+    // single segment is 1 - holes
+    pub fn euler_number(&self, connectivity: Connectivity) -> isize {
+        let components = self.clone().segment_all_with(connectivity).len();
 
-        for x in self.bounds.min_x..=self.bounds.max_x {
-            for y in self.bounds.min_y..=self.bounds.max_y {
-                if !self.contains(x, y) {
-                    invert.insert(x, y);
+        isize::try_from(components).unwrap()
+            - isize::try_from(self.num_holes(connectivity)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The outline of a square of the given size, with the given pixels left out
+    fn outline(size: u16, missing: &[(u16, u16)]) -> Pixels {
+        let mut pixels = Pixels::new();
+
+        for x in 0..size {
+            for y in 0..size {
+                let on_edge = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+
+                if on_edge && !missing.contains(&(x, y)) {
+                    pixels.insert(x, y);
                 }
             }
         }
 
-        invert.segment_all().len()
+        pixels
     }
 
-    fn contains(&self, x: u16, y: u16) -> bool {
-        if let Some(ys) = self.pixels.get(&x)
-            && ys.contains(&y)
-        {
-            true
-        } else {
-            false
+    #[test]
+    fn count_holes() {
+        let ring = outline(5, &[]);
+
+        assert_eq!(ring.num_holes(Connectivity::Four), 1);
+        assert_eq!(ring.num_holes(Connectivity::Eight), 1);
+        assert_eq!(ring.euler_number(Connectivity::Four), 0);
+
+        // A bar across the middle splits the hole in two
+        let mut split = outline(5, &[]);
+
+        for x in 1..4 {
+            split.insert(x, 2);
+        }
+
+        assert_eq!(split.num_holes(Connectivity::Four), 2);
+        assert_eq!(split.euler_number(Connectivity::Four), -1);
+
+        // An open side lets the background through
+        assert_eq!(outline(5, &[(2, 0)]).num_holes(Connectivity::Four), 0);
+
+        // A missing corner only connects the inside to the outside diagonally, which
+        // closes the hole for 8-connected pixels but not for 4-connected ones
+        let open_corner = outline(5, &[(0, 0)]);
+
+        assert_eq!(open_corner.num_holes(Connectivity::Four), 0);
+        assert_eq!(open_corner.num_holes(Connectivity::Eight), 1);
+    }
+
+    #[test]
+    fn open_away_lines() {
+        // A 9x9 square with a line one pixel wide sticking out of it
+        let mut pixels = Pixels::new();
+
+        for x in 0..9u16 {
+            for y in 0..9u16 {
+                pixels.insert(x, y);
+            }
+        }
+
+        for x in 9..20u16 {
+            pixels.insert(x, 4);
         }
+
+        let opened = pixels.opened(1);
+
+        assert_eq!(opened.len(), 81);
+        assert_eq!(opened.bounds.max_x, 8);
+        assert_eq!(pixels.opened(5).len(), 0);
+    }
+
+    #[test]
+    fn count_holes_of_empty_pixels() {
+        assert_eq!(Pixels::new().num_holes(Connectivity::Four), 0);
     }
 }
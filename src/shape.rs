@@ -0,0 +1,55 @@
+use crate::pixels::Pixels;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Shape {
+    Triangle,
+    Diamond,
+    Square,
+}
+
+// Classifies by the horizontal extent of each segment a quarter of the way down and
+// three quarters of the way down.  Squares are full width at both, triangles (which
+// point up) are much wider at the bottom, and diamonds are equally wide at both.
+pub fn classify_shape(segments: &[Pixels]) -> Shape {
+    let mut votes = [0; 3];
+
+    for segment in segments {
+        let bounds = segment.bounds;
+
+        let width = u32::from(bounds.max_x - bounds.min_x) + 1;
+        let height = bounds.max_y - bounds.min_y;
+
+        let upper = row_extent(segment, bounds.min_y + height / 4);
+        let lower = row_extent(segment, bounds.max_y - height / 4);
+
+        let shape = if 5 * upper > 4 * width && 5 * lower > 4 * width {
+            Shape::Square
+        } else if 2 * lower > 3 * upper {
+            Shape::Triangle
+        } else {
+            Shape::Diamond
+        };
+
+        votes[shape as usize] += 1;
+    }
+
+    if votes[0] >= votes[1] && votes[0] >= votes[2] {
+        Shape::Triangle
+    } else if votes[1] >= votes[2] {
+        Shape::Diamond
+    } else {
+        Shape::Square
+    }
+}
+
+fn row_extent(segment: &Pixels, y: u16) -> u32 {
+    let bounds = segment.bounds;
+
+    let mut xs = (bounds.min_x..=bounds.max_x).filter(|x| segment.contains(*x, y));
+
+    match (xs.next(), xs.next_back()) {
+        (Some(min_x), Some(max_x)) => u32::from(max_x - min_x) + 1,
+        (Some(_), None) => 1,
+        _ => 0,
+    }
+}
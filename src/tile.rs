@@ -1,6 +1,15 @@
-use crate::{bounds::Bounds, color_scheme::determine_color_scheme, image::Image, pixels::Pixels};
+use std::borrow::Cow;
 
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+use crate::{
+    Connectivity, Error,
+    board::Board,
+    bounds::Bounds,
+    color_scheme::{ColorScheme, border_color_candidates, determine_color_scheme},
+    detector::ColorTableDetector,
+    image::{Image, View},
+};
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Tile {
     pub grid_x: u16,
     pub grid_y: u16,
@@ -21,6 +30,21 @@ pub enum TileType {
     Connect4,
 }
 
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DetectOptions {
+    // Maximum RGB distance at which a pixel is still considered to have a given color
+    // scheme color.  Zero only accepts exact matches, which is also the fastest.
+    pub color_tolerance: u32,
+
+    // Whether to infer the color scheme from the image if the border color doesn't
+    // match any of the known color schemes
+    pub infer_color_scheme: bool,
+
+    // Connectivity of tile segments.  8-connectivity keeps thin antialiased
+    // diagonals together, at the cost of occasionally merging touching tiles.
+    pub connectivity: Connectivity,
+}
+
 impl Tile {
     pub fn new(bounds: &Bounds, tile_type: TileType) -> Self {
         let grid_x = (bounds.min_x + bounds.max_x) / 2;
@@ -38,130 +62,152 @@ impl Tile {
         }
     }
 
-    pub fn detect_tiles<T: Image + ?Sized>(image: &T) -> Result<Vec<Self>, String> {
-        let mut triangle_outer = Pixels::new();
-        let mut triangle = Pixels::new();
-        let mut diamond_outer = Pixels::new();
-        let mut diamond = Pixels::new();
-        let mut square_outer = Pixels::new();
-        let mut square = Pixels::new();
-        let mut connector = Pixels::new();
+    // Maps a tile detected within a view to the coordinate space of the view's parent
+    pub fn to_parent<T: Image + ?Sized>(&self, view: &View<T>) -> Result<Self, Error> {
+        let (x, y) = view.to_parent(u32::from(self.grid_x), u32::from(self.grid_y));
 
-        let color_scheme = {
-            let p = image.pixel(0, 0);
+        let (Ok(grid_x), Ok(grid_y)) = (u16::try_from(x), u16::try_from(y)) else {
+            return Err(Error::InvalidRegion(format!(
+                "Tile coordinates ({}, {}) out of range",
+                x, y
+            )));
+        };
 
-            determine_color_scheme(&[p.0, p.1, p.2])
-        }
-        .ok_or_else(|| "Can't determine color scheme for image".to_string())?;
-
-        for y in 0..image.height() {
-            for x in 0..image.width() {
-                let p = image.pixel(x, y);
-
-                let pixel = [p.0, p.1, p.2];
-
-                if pixel == color_scheme.triangle_outer_color {
-                    triangle_outer.insert(x, y);
-                } else if pixel == color_scheme.triangle_color {
-                    triangle.insert(x, y);
-                } else if pixel == color_scheme.diamond_outer_color {
-                    diamond_outer.insert(x, y);
-                } else if pixel == color_scheme.diamond_color {
-                    diamond.insert(x, y);
-                } else if pixel == color_scheme.square_outer_color {
-                    square_outer.insert(x, y);
-                } else if pixel == color_scheme.square_color {
-                    square.insert(x, y);
-                } else if pixel == color_scheme.connector_color {
-                    connector.insert(x, y);
-                }
-            }
-        }
+        Ok(Tile {
+            grid_x,
+            grid_y,
+            tile_type: self.tile_type,
+        })
+    }
 
-        let mut tiles = Vec::new();
+    // With a nonzero color tolerance, the border color may be close to the border
+    // colors of several schemes.  In that case, pick the scheme whose shape colors
+    // account for the most pixels in the image.
+    fn determine_color_scheme<T: Image + ?Sized>(
+        image: &T,
+        options: &DetectOptions,
+    ) -> Result<&'static ColorScheme, Error> {
+        let p = image.pixel(0, 0);
 
-        {
-            let triangle_outer = triangle_outer.segment_all();
-            let triangle = triangle.segment_all();
+        let border_color = [p.0, p.1, p.2];
 
-            if triangle.is_empty() {
-                assert!(triangle_outer.is_empty());
-            } else {
-                assert_eq!(triangle_outer.len(), 2);
-            }
+        if let Some(color_scheme) = determine_color_scheme(&border_color) {
+            return Ok(color_scheme);
+        }
 
-            for segment in &triangle_outer {
-                tiles.push(Tile::new(&segment.bounds, TileType::TriangleTerminal));
-            }
+        let candidates = border_color_candidates(&border_color, options.color_tolerance);
 
-            for segment in &triangle {
-                if !segment.is_within(&triangle_outer[0]) && !segment.is_within(&triangle_outer[1])
-                {
-                    tiles.push(Tile::new(&segment.bounds, TileType::Triangle));
-                }
-            }
-        }
+        let score = |color_scheme: &ColorScheme| {
+            let mut matching_pixels = 0;
 
-        {
-            let diamond_outer = diamond_outer.segment_all();
-            let diamond = diamond.segment_all();
+            for y in (0..image.height()).step_by(4) {
+                for x in (0..image.width()).step_by(4) {
+                    let p = image.pixel(x, y);
 
-            if diamond.is_empty() {
-                assert!(diamond_outer.is_empty());
-            } else {
-                assert_eq!(diamond_outer.len(), 2);
+                    if color_scheme
+                        .nearest_shape_color(&[p.0, p.1, p.2], options.color_tolerance)
+                        .is_some()
+                    {
+                        matching_pixels += 1;
+                    }
+                }
             }
 
-            for segment in &diamond_outer {
-                tiles.push(Tile::new(&segment.bounds, TileType::DiamondTerminal));
-            }
+            matching_pixels
+        };
 
-            for segment in &diamond {
-                if !segment.is_within(&diamond_outer[0]) && !segment.is_within(&diamond_outer[1]) {
-                    tiles.push(Tile::new(&segment.bounds, TileType::Diamond));
-                }
+        match candidates.as_slice() {
+            [] => Err(Error::UnknownColorScheme(border_color)),
+            [color_scheme] => Ok(color_scheme),
+            _ => Ok(candidates
+                .iter()
+                .max_by_key(|color_scheme| score(color_scheme))
+                .unwrap()),
+        }
+    }
+
+    // Determines the color scheme of the image from its border color, falling back
+    // to inferring one if the options allow that
+    pub fn detect_color_scheme<T: Image + ?Sized>(
+        image: &T,
+        options: &DetectOptions,
+    ) -> Result<Cow<'static, ColorScheme>, Error> {
+        match Self::determine_color_scheme(image, options) {
+            Ok(color_scheme) => Ok(Cow::Borrowed(color_scheme)),
+            Err(Error::UnknownColorScheme(_)) if options.infer_color_scheme => {
+                Ok(Cow::Owned(ColorScheme::infer(image)?))
             }
+            Err(err) => Err(err),
         }
+    }
 
-        {
-            let square_outer = square_outer.segment_all();
-            let square = square.segment_all();
+    pub fn detect_tiles<T: Image + ?Sized>(image: &T) -> Result<Vec<Self>, Error> {
+        Self::detect_tiles_with(image, &DetectOptions::default())
+    }
 
-            if square.is_empty() {
-                assert!(square_outer.is_empty());
-            } else {
-                assert_eq!(square_outer.len(), 2);
-            }
+    // Detects the tiles of the board within the image, by their colors
+    pub fn detect_tiles_with<T: Image + ?Sized>(
+        image: &T,
+        options: &DetectOptions,
+    ) -> Result<Vec<Self>, Error> {
+        let detector = ColorTableDetector {
+            color_scheme: None,
+            options: *options,
+        };
 
-            for segment in &square_outer {
-                tiles.push(Tile::new(&segment.bounds, TileType::SquareTerminal));
-            }
+        Ok(Board::locate(image, options)?
+            .detect(image, &detector, None)?
+            .tiles())
+    }
 
-            for segment in &square {
-                if !segment.is_within(&square_outer[0]) && !segment.is_within(&square_outer[1]) {
-                    tiles.push(Tile::new(&segment.bounds, TileType::Square));
-                }
-            }
-        }
+    // Detects the tiles within a region of interest of the image.  The returned tile
+    // coordinates are relative to the full image.
+    pub fn detect_tiles_in<T: Image + ?Sized>(
+        image: &T,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        options: &DetectOptions,
+    ) -> Result<Vec<Self>, Error> {
+        let view = View::new(image, x, y, width, height)?;
+
+        Self::detect_tiles_with(&view, options)?
+            .into_iter()
+            .map(|tile| tile.to_parent(&view))
+            .collect()
+    }
+}
 
-        for segment in &connector.segment_all() {
-            let num_holes = segment.num_holes();
-
-            tiles.push(Tile::new(
-                &segment.bounds,
-                match num_holes {
-                    6 => TileType::Connect2,
-                    7 => TileType::Connect3,
-                    8 => TileType::Connect4,
-                    _ => panic!(),
-                },
-            ));
-        }
+// A shape's path runs between its two terminals, so a shape either has two of them
+// or doesn't appear on the board at all
+pub(crate) fn check_terminal_count(shape: &str, terminals: usize) -> Result<(), Error> {
+    if terminals != 0 && terminals != 2 {
+        return Err(Error::MalformedTileLayout(format!(
+            "Expected 2 {} terminals, found {}",
+            shape, terminals
+        )));
+    }
 
-        tiles.sort();
+    Ok(())
+}
 
-        Ok(tiles)
+// Checks that each shape has either two terminals or none
+pub(crate) fn check_terminal_counts(tiles: &[Tile]) -> Result<(), Error> {
+    for (shape, is_shape) in [
+        ("triangle", TileType::is_triangle as fn(&TileType) -> bool),
+        ("diamond", TileType::is_diamond),
+        ("square", TileType::is_square),
+    ] {
+        let terminals = tiles
+            .iter()
+            .filter(|tile| is_shape(&tile.tile_type) && tile.tile_type.is_terminal())
+            .count();
+
+        check_terminal_count(shape, terminals)?;
     }
+
+    Ok(())
 }
 
 impl TileType {
@@ -177,6 +223,12 @@ impl TileType {
         *self == TileType::SquareTerminal || *self == TileType::Square
     }
 
+    pub fn is_terminal(&self) -> bool {
+        *self == TileType::TriangleTerminal
+            || *self == TileType::DiamondTerminal
+            || *self == TileType::SquareTerminal
+    }
+
     pub fn is_connector(&self) -> bool {
         *self == TileType::Connect2 || *self == TileType::Connect3 || *self == TileType::Connect4
     }
@@ -196,3 +248,45 @@ impl TileType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(grid_x: u16, tile_type: TileType) -> Tile {
+        Tile {
+            grid_x,
+            grid_y: 0,
+            tile_type,
+        }
+    }
+
+    #[test]
+    fn terminal_count() {
+        assert!(check_terminal_count("square", 0).is_ok());
+        assert!(check_terminal_count("square", 2).is_ok());
+        assert!(check_terminal_count("square", 1).is_err());
+        assert!(check_terminal_count("square", 3).is_err());
+    }
+
+    #[test]
+    fn terminals_without_intermediates() {
+        let tiles = [
+            tile(0, TileType::SquareTerminal),
+            tile(10, TileType::SquareTerminal),
+        ];
+
+        assert!(check_terminal_counts(&tiles).is_ok());
+    }
+
+    #[test]
+    fn missing_terminal() {
+        let tiles = [
+            tile(0, TileType::TriangleTerminal),
+            tile(10, TileType::Triangle),
+            tile(20, TileType::Diamond),
+        ];
+
+        assert!(check_terminal_counts(&tiles).is_err());
+    }
+}